@@ -0,0 +1,49 @@
+use embedded_hal::PwmPin;
+use rp_pico::hal::pwm::{FreeRunning, Pwm0, Slice};
+
+/// A piezo buzzer connected between GPIO16 and GPIO17 (PWM slice 0, channels A and B).
+///
+/// The channel B output is inverted while a tone is playing, so the piezo sees twice the swing.
+pub struct Buzzer {
+    pwm: Slice<Pwm0, FreeRunning>,
+    sys_clk_hz: u32,
+}
+
+impl Buzzer {
+    /// The GPIO pins must be already assigned to the PWM slice.
+    pub fn new(mut pwm: Slice<Pwm0, FreeRunning>, sys_clk_hz: u32) -> Self {
+        pwm.default_config();
+        pwm.enable();
+
+        let mut buzzer = Self { pwm, sys_clk_hz };
+        buzzer.silence();
+        buzzer
+    }
+
+    /// Play a square wave at `tone_hz`, 0 Hz being silence.
+    pub fn tone(&mut self, tone_hz: u16) {
+        if tone_hz == 0 {
+            self.silence();
+            return;
+        }
+
+        // The counter wraps at `top + 1`, the integer divider keeps `top` within 16 bits.
+        let div = (self.sys_clk_hz / (tone_hz as u32 * 0x10000) + 1).min(u8::MAX as u32);
+        let top = (self.sys_clk_hz / (div * tone_hz as u32)).clamp(2, 0x10000) - 1;
+
+        self.pwm.set_div_int(div as u8);
+        self.pwm.set_div_frac(0);
+        self.pwm.set_top(top as u16);
+        self.pwm.set_counter(0);
+        self.pwm.channel_b.set_inverted();
+        self.pwm.channel_a.set_duty((top / 2) as u16);
+        self.pwm.channel_b.set_duty((top / 2) as u16);
+    }
+
+    pub fn silence(&mut self) {
+        // Both outputs low.
+        self.pwm.channel_b.clr_inverted();
+        self.pwm.channel_a.set_duty(0);
+        self.pwm.channel_b.set_duty(0);
+    }
+}
@@ -1,5 +1,9 @@
 use rp_pico::pac;
-use seg_disp::char7dp::Char7DP;
+use seg_disp::disp::Action;
+
+/// The segment pins followed by the digit select pins.
+pub const GPIO_DISP_OFFSET: u32 = GPIO_SEG_OFFSET;
+pub const GPIO_DISP_COUNT: u32 = 12;
 
 const GPIO_SEG_OFFSET: u32 = 1;
 const GPIO_SEL_OFFSET: u32 = 9;
@@ -16,17 +20,23 @@ pub fn seg_disp_configure(io_bank0: &pac::IO_BANK0, sio: &pac::SIO) {
         .write(|w| unsafe { w.bits(0xff << GPIO_SEG_OFFSET | 0x0f << GPIO_SEL_OFFSET) });
 }
 
-pub fn seg_disp_update(char7dp: Char7DP, digit_index: usize, sio: &pac::SIO) {
-    sio.gpio_out_set.write(|w| unsafe {
-        w.bits(
-            (char7dp.state() as u32) << GPIO_SEG_OFFSET
-                | (1 << digit_index & 0x0f) << GPIO_SEL_OFFSET,
-        )
-    });
-    sio.gpio_out_clr.write(|w| unsafe {
-        w.bits(
-            (!char7dp.state() as u32) << GPIO_SEG_OFFSET
-                | (!(1 << digit_index) & 0x0f) << GPIO_SEL_OFFSET,
-        )
-    });
+/// The states of the display pins for the action, bit 0 is [`GPIO_DISP_OFFSET`].
+pub fn seg_disp_pins(action: Action) -> u32 {
+    let (segments, digits) = match action {
+        Action::Render(char7dp, digit_index) => (char7dp.state(), 1 << digit_index),
+        Action::RenderSegment(segment, digits) => (segment as u8, digits),
+    };
+
+    (segments as u32) << (GPIO_SEG_OFFSET - GPIO_DISP_OFFSET)
+        | (digits & 0x0f) << (GPIO_SEL_OFFSET - GPIO_DISP_OFFSET)
+}
+
+pub fn seg_disp_update(action: Action, sio: &pac::SIO) {
+    const PINS_MASK: u32 = (1 << GPIO_DISP_COUNT) - 1;
+    let pins = seg_disp_pins(action);
+
+    sio.gpio_out_set
+        .write(|w| unsafe { w.bits(pins << GPIO_DISP_OFFSET) });
+    sio.gpio_out_clr
+        .write(|w| unsafe { w.bits((!pins & PINS_MASK) << GPIO_DISP_OFFSET) });
 }
@@ -0,0 +1,190 @@
+use rp_pico::{
+    hal::pio::{
+        Buffers, PIOBuilder, PIOExt, PinDir, Running, ShiftDirection, StateMachine, PIO, SM0,
+    },
+    pac,
+};
+use seg_disp::disp::Frame;
+
+use crate::display::{seg_disp_pins, GPIO_DISP_COUNT, GPIO_DISP_OFFSET};
+
+/// Refreshes the display from a frame buffer without the CPU.
+///
+/// PIO0 SM0 drives the display pins by the words of the buffer, each word holding
+/// the pin states and the number of microseconds to hold them for. The DMA channel
+/// [`DATA_CH`] streams the buffer into the state machine and chains to [`CONTROL_CH`],
+/// which restarts it from `FrameBuffers::frame_addr`. A new frame is written into
+/// the spare buffer and takes over at the end of the frame being streamed.
+pub struct DisplayStream {
+    _pio: PIO<pac::PIO0>,
+    _sm: StateMachine<(pac::PIO0, SM0), Running>,
+    dma: pac::DMA,
+    buffers: &'static mut FrameBuffers,
+    /// The index of the buffer being streamed.
+    front: usize,
+}
+
+struct FrameBuffers {
+    frames: [[u32; FRAME_WORDS]; 2],
+    /// The address of the buffer to stream next, read by the DMA.
+    frame_addr: u32,
+}
+
+const DATA_CH: usize = 0;
+const CONTROL_CH: usize = 1;
+// Enough for the segment scan (8 segments, on and off), shorter frames are padded.
+const FRAME_WORDS: usize = 16;
+// The PIO runs at 1 MHz, so the hold counts are in microseconds.
+const PIO_CLOCK_HZ: u32 = 1_000_000;
+// Both `out` instructions and the last iteration of the hold loop.
+const WORD_OVERHEAD_CYCLES: u32 = 3;
+const HOLD_COUNT_MAX: u32 = (1 << (32 - GPIO_DISP_COUNT)) - 1;
+
+impl DisplayStream {
+    pub fn new(
+        pio0: pac::PIO0,
+        dma: pac::DMA,
+        io_bank0: &pac::IO_BANK0,
+        resets: &mut pac::RESETS,
+        sys_clk_hz: u32,
+    ) -> Self {
+        for i in GPIO_DISP_OFFSET..(GPIO_DISP_OFFSET + GPIO_DISP_COUNT) {
+            const GPIO_FUNC_PIO0: u8 = 6;
+            io_bank0.gpio[i as usize]
+                .gpio_ctrl
+                .write(|w| unsafe { w.funcsel().bits(GPIO_FUNC_PIO0) });
+        }
+
+        // .wrap_target
+        //     out pins, 12
+        //     out x, 20
+        // hold:
+        //     jmp x-- hold
+        // .wrap
+        let program = {
+            let mut a = pio::Assembler::<{ pio::RP2040_MAX_PROGRAM_SIZE }>::new();
+            let mut wrap_target = a.label();
+            let mut wrap_source = a.label();
+            let mut hold = a.label();
+            a.bind(&mut wrap_target);
+            a.out(pio::OutDestination::PINS, GPIO_DISP_COUNT as u8);
+            a.out(pio::OutDestination::X, 32 - GPIO_DISP_COUNT as u8);
+            a.bind(&mut hold);
+            a.jmp(pio::JmpCondition::XDecNonZero, &mut hold);
+            a.bind(&mut wrap_source);
+            a.assemble_with_wrap(wrap_source, wrap_target)
+        };
+
+        let (mut pio, sm0, _, _, _) = pio0.split(resets);
+        let program = pio.install(&program).unwrap();
+        let (mut sm, _, tx) = PIOBuilder::from_program(program)
+            .out_pins(GPIO_DISP_OFFSET as u8, GPIO_DISP_COUNT as u8)
+            .out_shift_direction(ShiftDirection::Right)
+            // Pulls every 32 bits (the default threshold).
+            .autopull(true)
+            .buffers(Buffers::OnlyTx)
+            .clock_divisor_fixed_point(
+                (sys_clk_hz / PIO_CLOCK_HZ) as u16,
+                ((sys_clk_hz % PIO_CLOCK_HZ) * 256 / PIO_CLOCK_HZ) as u8,
+            )
+            .build(sm0);
+        sm.set_pindirs(
+            (GPIO_DISP_OFFSET..(GPIO_DISP_OFFSET + GPIO_DISP_COUNT))
+                .map(|i| (i as u8, PinDir::Output)),
+        );
+        let sm = sm.start();
+
+        let buffers = cortex_m::singleton!(: FrameBuffers = FrameBuffers {
+            frames: [[0; FRAME_WORDS]; 2],
+            frame_addr: 0,
+        })
+        .unwrap();
+        buffers.frame_addr = buffers.frames[0].as_ptr() as u32;
+
+        resets.reset.modify(|_, w| w.dma().clear_bit());
+        while resets.reset_done.read().dma().bit_is_clear() {}
+
+        let data = &dma.ch[DATA_CH];
+        data.ch_write_addr
+            .write(|w| unsafe { w.bits(tx.fifo_address() as u32) });
+        data.ch_trans_count
+            .write(|w| unsafe { w.bits(FRAME_WORDS as u32) });
+        data.ch_al1_ctrl.write(|w| unsafe {
+            w.data_size()
+                .size_word()
+                .incr_read()
+                .set_bit()
+                .incr_write()
+                .clear_bit()
+                .treq_sel()
+                .bits(tx.dreq_value())
+                .chain_to()
+                .bits(CONTROL_CH as u8)
+                .en()
+                .set_bit()
+        });
+
+        let control = &dma.ch[CONTROL_CH];
+        control
+            .ch_read_addr
+            .write(|w| unsafe { w.bits(&buffers.frame_addr as *const u32 as u32) });
+        control
+            .ch_write_addr
+            .write(|w| unsafe { w.bits(data.ch_al3_read_addr_trig.as_ptr() as u32) });
+        control.ch_trans_count.write(|w| unsafe { w.bits(1) });
+        control.ch_al1_ctrl.write(|w| unsafe {
+            w.data_size()
+                .size_word()
+                .incr_read()
+                .clear_bit()
+                .incr_write()
+                .clear_bit()
+                .treq_sel()
+                .permanent()
+                // Chaining to itself disables the chaining.
+                .chain_to()
+                .bits(CONTROL_CH as u8)
+                .en()
+                .set_bit()
+        });
+
+        dma.multi_chan_trigger
+            .write(|w| unsafe { w.bits(1 << CONTROL_CH) });
+
+        Self {
+            _pio: pio,
+            _sm: sm,
+            dma,
+            buffers,
+            front: 0,
+        }
+    }
+
+    pub fn set_frame<const N: usize>(&mut self, frame: &Frame<N>) {
+        let back = 1 - self.front;
+        let back_range = self.buffers.frames[back].as_ptr_range();
+
+        // The spare buffer may still be streamed right after the previous swap.
+        while back_range.contains(&(self.dma.ch[DATA_CH].ch_read_addr.read().bits() as *const u32))
+        {
+        }
+
+        let words = frame
+            .steps()
+            .map(|(action, duration)| {
+                let hold = (duration.ticks() as u32)
+                    .saturating_sub(WORD_OVERHEAD_CYCLES)
+                    .min(HOLD_COUNT_MAX);
+                seg_disp_pins(action) | hold << GPIO_DISP_COUNT
+            })
+            .chain(core::iter::repeat(0));
+        for (word, value) in self.buffers.frames[back].iter_mut().zip(words) {
+            *word = value;
+        }
+
+        unsafe {
+            core::ptr::write_volatile(&mut self.buffers.frame_addr, back_range.start as u32);
+        }
+        self.front = back;
+    }
+}
@@ -1,10 +1,7 @@
 #![no_std]
 #![no_main]
 
-extern crate alloc;
-
-use alloc::boxed::Box;
-use core::panic::PanicInfo;
+use core::{cell::RefCell, panic::PanicInfo};
 use embedded_hal::{
     digital::v2::{InputPin, OutputPin},
     prelude::_embedded_hal_adc_OneShot,
@@ -12,35 +9,60 @@ use embedded_hal::{
 
 use app_core::{
     action::Action,
+    calendar,
     common::Duration,
-    features::charger::ChargerAction,
+    features::{
+        charger::ChargerAction, display::Refresh, power::PowerAction, set_time::ClockAction,
+        sound::SoundAction,
+    },
     state::State,
-    task::{scheduler::Scheduler, FnTask, NextRun, Task},
+    task::{
+        scheduler::{Deadline, StaticScheduler},
+        ActionBuffer, InlineFnTask, NextRun, Task,
+    },
 };
-use embedded_alloc::Heap;
 use rp_pico::{
     entry,
     hal::pac,
-    hal::{self, gpio::PinState},
+    hal::{self, gpio::PinState, Clock},
 };
 use rtt_target::{rprintln, rtt_init_print};
+use seg_disp::disp::ScanMode;
 
 use crate::{
+    buzzer::Buzzer,
     display::{seg_disp_configure, seg_disp_update},
+    display_stream::DisplayStream,
+    settings_store::{Record, SettingsStore},
+    touch::{touch_configure, touch_measure},
     uptime::Uptime,
 };
 
+mod buzzer;
 mod display;
+mod display_stream;
+mod power;
+mod settings_store;
+mod touch;
 mod uptime;
 mod uptime_delay;
 
+/// Either every multiplexing step is run by the CPU, or the display is refreshed by PIO and DMA.
+const DISPLAY_REFRESH: Refresh = Refresh::Streamed;
+/// Either one digit or one segment across the digits is lit at a time, the latter keeping
+/// the current constant at the cost of the brightness compensation.
+const DISPLAY_SCAN_MODE: ScanMode = ScanMode::Digit;
+/// How often the scheduler statistics are printed over RTT, in the debug builds.
+#[cfg(debug_assertions)]
+const STATS_REPORT_PERIOD_US: u64 = 10_000_000;
+
 #[entry]
 fn main() -> ! {
     rtt_init_print!();
-    init_heap();
 
     let mut pac = pac::Peripherals::take().unwrap();
     let core = pac::CorePeripherals::take().unwrap();
+    let mut scb = core.SCB;
 
     let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
 
@@ -65,11 +87,9 @@ fn main() -> ! {
         &mut pac.RESETS,
     );
 
-    let rtc = hal::rtc::RealTimeClock::new(
-        pac.RTC,
-        clocks.rtc_clock,
-        &mut pac.RESETS,
-        hal::rtc::DateTime {
+    let mut settings_store = SettingsStore::new();
+    let record = settings_store.load().unwrap_or(Record {
+        datetime: hal::rtc::DateTime {
             year: 2023,
             month: 4,
             day: 7,
@@ -78,10 +98,18 @@ fn main() -> ! {
             minute: 1,
             second: 0,
         },
-    )
-    .unwrap();
+        settings: Default::default(),
+    });
 
-    let uptime = Uptime::new(core.SYST, 5);
+    let mut rtc =
+        hal::rtc::RealTimeClock::new(pac.RTC, clocks.rtc_clock, &mut pac.RESETS, record.datetime)
+            .unwrap();
+    power::rtc_clock_configure(&mut rtc);
+    let rtc: &'static RefCell<_> =
+        cortex_m::singleton!(: RefCell<hal::rtc::RealTimeClock> = RefCell::new(rtc)).unwrap();
+
+    let mut uptime = Uptime::new(pac.TIMER, &mut pac.RESETS);
+    let mut sleeper = power::Sleeper::new();
 
     fn adc_f32(value: u16) -> f32 {
         const ADC_MAX: u16 = 0x0fff;
@@ -93,61 +121,179 @@ fn main() -> ! {
     let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
     let mut bat_v1_pin = pins.gpio26.into_floating_input();
     let mut bat_v2_pin = pins.gpio27.into_floating_input();
+    let mut temp_sense = adc.enable_temp_sensor();
 
     let ext_power_detect_pin = pins.vbus_detect.into_floating_input();
 
     let mut ncharge_pin = pins.gpio18.into_push_pull_output_in_state(PinState::High);
 
-    let pac = unsafe { pac::Peripherals::steal() };
-    seg_disp_configure(&pac.IO_BANK0, &pac.SIO);
+    let mut buzzer = {
+        let mut pwm = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS).pwm0;
+        pwm.channel_a.output_to(pins.gpio16);
+        pwm.channel_b.output_to(pins.gpio17);
+        Buzzer::new(pwm, clocks.system_clock.freq().to_Hz())
+    };
+
+    let stolen_pac = unsafe { pac::Peripherals::steal() };
+    let mut display_stream = match DISPLAY_REFRESH {
+        Refresh::Scheduled => {
+            seg_disp_configure(&stolen_pac.IO_BANK0, &stolen_pac.SIO);
+            None
+        }
+        Refresh::Streamed => Some(DisplayStream::new(
+            pac.PIO0,
+            pac.DMA,
+            &stolen_pac.IO_BANK0,
+            &mut pac.RESETS,
+            clocks.system_clock.freq().to_Hz(),
+        )),
+    };
+
+    let pac = stolen_pac;
+    touch_configure(&pac.IO_BANK0, &pac.PADS_BANK0, &pac.SIO);
+    let touch_sio = unsafe { pac::Peripherals::steal() }.SIO;
 
-    let app_display = app_core::features::display::Display::default();
+    let mut app_display =
+        app_core::features::display::Display::new(DISPLAY_REFRESH, DISPLAY_SCAN_MODE);
     let mut app_charger = app_core::features::charger::Charger::default();
+    let mut app_touch = app_core::features::touch::Touch::default();
+    let mut app_set_time = app_core::features::set_time::SetTime::default();
+    let mut app_set_alarm = app_core::features::set_alarm::SetAlarm::default();
+    let mut app_date_view = app_core::features::date_view::DateView::default();
+    let mut app_alarm_clock = app_core::features::alarm::AlarmClock::default();
+    let mut app_sequencer = app_core::features::sound::Sequencer::default();
+    let mut app_power_saver = app_core::features::power::PowerSaver::default();
 
-    let mut scheduler = Scheduler::<State, Action>::new([
-        Box::new(FnTask::new(move |state: &mut State| {
-            let now = rtc.now().unwrap();
-
-            state.rtc = app_core::state::RTC {
-                hour: now.hour,
-                minute: now.minute,
-                second: now.second,
-            };
-
-            (None, NextRun::After(Duration::from_ticks(200_000)))
-        })) as _,
-        Box::new(app_display) as _,
-        Box::new(FnTask::new(move |state: &mut State| {
-            let mut sum1 = 0;
-            let mut sum2 = 0;
-            const N: u16 = 16;
-            for _ in 0..N {
-                let v1: u16 = adc.read(&mut bat_v1_pin).unwrap();
-                sum1 += v1;
-                let v2: u16 = adc.read(&mut bat_v2_pin).unwrap();
-                sum2 += v2;
-            }
-            sum2 -= sum1;
-            let v1 = adc_f32(sum1) / N as f32;
-            let v2 = adc_f32(sum2) / N as f32;
+    let mut rtc_task = InlineFnTask::new(move |state: &mut State| {
+        let now = rtc.borrow().now().unwrap();
+
+        state.rtc = app_core::state::RTC {
+            year: now.year,
+            month: now.month,
+            day: now.day,
+            day_of_week: now.day_of_week as u8,
+            hour: now.hour,
+            minute: now.minute,
+            second: now.second,
+        };
+
+        (None, NextRun::After(Duration::from_ticks(200_000)))
+    });
+    // The wrappers carry over the priorities and the subscriptions of the wrapped tasks.
+    let (charger_priority, charger_subscriptions) =
+        (app_charger.priority(), app_charger.subscriptions());
+    let mut charger_task = InlineFnTask::new(move |state: &mut State| {
+        let mut sum1 = 0;
+        let mut sum2 = 0;
+        let mut sum_t = 0;
+        const N: u16 = 16;
+        for _ in 0..N {
+            let v1: u16 = adc.read(&mut bat_v1_pin).unwrap();
+            sum1 += v1;
+            let v2: u16 = adc.read(&mut bat_v2_pin).unwrap();
+            sum2 += v2;
+            let t: u16 = adc.read(&mut temp_sense).unwrap();
+            sum_t += t;
+        }
+        sum2 -= sum1;
+        let v1 = adc_f32(sum1) / N as f32;
+        let v2 = adc_f32(sum2) / N as f32;
+        let v_t = adc_f32(sum_t) / N as f32;
+
+        let (g1, g2) = state.settings.bat_voltage_gain;
+        state.bat_voltage = (v1 * g1, v2 * g2);
+        // The RP2040 die temperature (see the datasheet, 4.9.5), the batteries being
+        // on the same board.
+        state.bat_temperature = Some(27.0 - (v_t - 0.706) / 0.001721);
+
+        app_charger.run(state)
+    })
+    .with_priority(charger_priority)
+    .with_subscriptions(charger_subscriptions);
+    // Sampled more often than the charger runs, which is woken up by the change.
+    let mut ext_power_task = InlineFnTask::new(move |state: &mut State| {
+        state.set_ext_power(ext_power_detect_pin.is_high().unwrap());
+
+        (None, NextRun::After(Duration::from_ticks(100_000)))
+    });
+    let (touch_priority, touch_subscriptions) = (app_touch.priority(), app_touch.subscriptions());
+    let mut touch_task = InlineFnTask::new(move |state: &mut State| {
+        for (i, count) in state.touch.iter_mut().enumerate() {
+            *count = touch_measure(i, &touch_sio);
+        }
 
-            state.bat_voltage = (v1, v2);
-            state.ext_power = ext_power_detect_pin.is_high().unwrap();
+        app_touch.run(state)
+    })
+    .with_priority(touch_priority)
+    .with_subscriptions(touch_subscriptions);
 
-            app_charger.run(state)
-        })) as _,
-    ]);
+    // Named for the statistics.
+    let tasks: [(
+        &str,
+        &mut dyn app_core::task::MultiActionTask<State, Action>,
+    ); 11] = [
+        ("rtc", &mut rtc_task),
+        ("display", &mut app_display),
+        ("charger", &mut charger_task),
+        ("ext_power", &mut ext_power_task),
+        ("touch", &mut touch_task),
+        ("set_time", &mut app_set_time),
+        ("set_alarm", &mut app_set_alarm),
+        ("date_view", &mut app_date_view),
+        ("alarm_clock", &mut app_alarm_clock),
+        ("sequencer", &mut app_sequencer),
+        ("power_saver", &mut app_power_saver),
+    ];
+    #[cfg(debug_assertions)]
+    let task_names = tasks.each_ref().map(|&(name, _)| name);
+    let mut scheduler = StaticScheduler::new(tasks.map(|(_, task)| task));
 
-    let mut state = State::default();
+    let mut state = State {
+        settings: record.settings,
+        ..Default::default()
+    };
+
+    #[cfg(debug_assertions)]
+    let stats_report_period = Duration::from_ticks(STATS_REPORT_PERIOD_US);
+    #[cfg(debug_assertions)]
+    let mut stats_report_at = uptime.get_instant() + stats_report_period;
+
+    // A single task run rarely emits more than a couple of actions.
+    let mut actions = ActionBuffer::<Action, 4>::default();
 
     loop {
-        if let Some(action) = scheduler.run(uptime.get_instant(), &mut state) {
+        let now = uptime.get_instant();
+        #[cfg(debug_assertions)]
+        if now >= stats_report_at {
+            for (name, stats) in task_names.iter().zip(scheduler.stats()) {
+                rprintln!(
+                    "{}: {} runs, {} missed, {} us max late",
+                    name,
+                    stats.run_count,
+                    stats.missed_deadlines,
+                    stats.max_lateness.ticks()
+                );
+            }
+            if actions.dropped() > 0 {
+                rprintln!("{} actions dropped", actions.dropped());
+            }
+            scheduler.reset_stats();
+            stats_report_at = now + stats_report_period;
+        }
+
+        state.now = now;
+        scheduler.run(now, &mut state, &mut actions);
+        scheduler.wake(now, state.events.take_published());
+        for action in actions.drain() {
             match action {
-                Action::Display(action) => match action {
-                    seg_disp::disp::Action::Render(c, i) => {
-                        seg_disp_update(c, i, &pac.SIO);
+                Action::Display(action) => {
+                    seg_disp_update(action, &pac.SIO);
+                }
+                Action::DisplayFrame(frame) => {
+                    if let Some(display_stream) = &mut display_stream {
+                        display_stream.set_frame(&frame);
                     }
-                },
+                }
                 Action::Battery(action) => match action {
                     ChargerAction::Charge => {
                         ncharge_pin.set_low().unwrap();
@@ -156,19 +302,61 @@ fn main() -> ! {
                         ncharge_pin.set_high().unwrap();
                     }
                 },
+                Action::Clock(action) => match action {
+                    ClockAction::SetTime {
+                        year,
+                        month,
+                        day,
+                        hour,
+                        minute,
+                    } => {
+                        let mut rtc = rtc.borrow_mut();
+                        let day_of_week =
+                            settings_store::day_of_week(calendar::day_of_week(year, month, day))
+                                .unwrap();
+                        let datetime = hal::rtc::DateTime {
+                            year,
+                            month,
+                            day,
+                            day_of_week,
+                            hour,
+                            minute,
+                            second: 0,
+                        };
+                        settings_store.save(&Record {
+                            datetime: hal::rtc::DateTime { ..datetime },
+                            settings: state.settings,
+                        });
+                        rtc.set_datetime(datetime).unwrap();
+                        sleeper.resync();
+                    }
+                },
+                Action::SaveSettings(settings) => {
+                    let datetime = rtc.borrow().now().unwrap();
+                    settings_store.save(&Record { datetime, settings });
+                }
+                Action::Alarm(_) => {
+                    // The sound is sequenced by `app_sequencer`, the display flashes on its own.
+                }
+                Action::Sound(action) => match action {
+                    SoundAction::Tone(tone_hz) => buzzer.tone(tone_hz),
+                    SoundAction::Silence => buzzer.silence(),
+                },
+                Action::Power(action) => match action {
+                    PowerAction::Sleep => {
+                        sleeper.sleep(&mut rtc.borrow_mut(), &mut scb, &mut uptime);
+                    }
+                },
             }
         }
-    }
-}
-
-#[global_allocator]
-static HEAP: Heap = Heap::empty();
 
-fn init_heap() {
-    use core::mem::MaybeUninit;
-    const HEAP_SIZE: usize = 64 * 1024;
-    static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
-    unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) }
+        match scheduler.next_deadline() {
+            Deadline::Now => {}
+            Deadline::At(deadline) => uptime.sleep_until(deadline),
+            // Nothing left to run, short of an interrupt.
+            Deadline::Never => cortex_m::asm::wfi(),
+        }
+    }
 }
 
 #[panic_handler]
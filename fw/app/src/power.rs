@@ -0,0 +1,248 @@
+use cortex_m::{
+    interrupt,
+    peripheral::{NVIC, SCB},
+};
+use rp_pico::{
+    hal::rtc::{DateTimeFilter, RealTimeClock},
+    pac,
+};
+
+use app_core::common::{Duration, Instant};
+
+use crate::{touch::GPIO_TCH, uptime::Uptime};
+
+/// The GPIO pin connected to the 32.768 kHz oscillator (RTC_CLK), GPIN0.
+const GPIO_RTC_CLK: usize = 20;
+const RTC_CLK_HZ: u32 = 32_768;
+/// The GPIO pin sensing VBUS, high with the external power connected.
+const GPIO_VBUS_DETECT: u32 = 24;
+
+/// Clock the RTC from the 32.768 kHz oscillator on GPIN0, which keeps running in the DORMANT state.
+///
+/// The HAL clocks the RTC from PLL_USB, which stops in DORMANT along with the XOSC. The RTC
+/// is stopped while switching, and restarted from the time it was at.
+pub fn rtc_clock_configure(rtc: &mut RealTimeClock) {
+    let datetime = rtc.now().unwrap();
+
+    // Safety: the registers are only touched before the RTC is in use.
+    let io_bank0 = unsafe { &*pac::IO_BANK0::ptr() };
+    let clocks = unsafe { &*pac::CLOCKS::ptr() };
+    let rtc_regs = unsafe { &*pac::RTC::ptr() };
+
+    io_bank0.gpio[GPIO_RTC_CLK]
+        .gpio_ctrl
+        .write(|w| w.funcsel().clock());
+
+    rtc_regs.ctrl.modify(|_, w| w.rtc_enable().clear_bit());
+    while rtc_regs.ctrl.read().rtc_active().bit_is_set() {}
+
+    // The auxiliary source is only switched glitch-free with the clock stopped, which takes
+    // a couple of its cycles (at 46.875 kHz from PLL_USB).
+    clocks.clk_rtc_ctrl.modify(|_, w| w.enable().clear_bit());
+    cortex_m::asm::delay(CLK_RTC_STOP_CYCLES);
+    clocks.clk_rtc_ctrl.modify(|_, w| w.auxsrc().clksrc_gpin0());
+    // The integer divisor of 1, in the upper 24 bits.
+    clocks.clk_rtc_div.write(|w| unsafe { w.bits(1 << 8) });
+    clocks.clk_rtc_ctrl.modify(|_, w| w.enable().set_bit());
+
+    rtc_regs
+        .clkdiv_m1
+        .write(|w| unsafe { w.bits(RTC_CLK_HZ - 1) });
+    rtc.set_datetime(datetime).unwrap();
+}
+
+/// Puts the chip to sleep until the next RTC second or a GPIO edge, keeping the uptime in step.
+///
+/// The DORMANT state stops the XOSC, and so the PLLs and the TIMER, only the RTC keeps running
+/// from its own oscillator (see [`rtc_clock_configure()`]). The time spent in DORMANT is told
+/// by the RTC, against a sync point: the uptime at an RTC second boundary. A wake-up by the RTC
+/// alarm is at a second boundary and makes for a new sync point. A wake-up by a GPIO edge is
+/// somewhere within a second, the uptime is caught up with the beginning of that second and
+/// stays up to a second behind until the next wake-up by the RTC. Without a recent sync point
+/// the chip enters the SLEEP state instead, in which the TIMER keeps running, until the next
+/// RTC second.
+///
+/// Connecting the external power wakes the chip up, and so may a touch: the pads are left
+/// to the pull-ups, and a finger pulls a pad down for a moment by the charge it takes. Whether
+/// that makes for an edge depends on the pad and the finger, so the pads are also polled after
+/// every wake-up by the RTC, once a second.
+pub struct Sleeper {
+    /// The uptime at an RTC second boundary and the second of day it started.
+    sync: Option<(Instant, u32)>,
+}
+
+impl Sleeper {
+    pub fn new() -> Self {
+        // Unused with the clocks running from the XOSC, it would keep running in DORMANT.
+        let rosc = unsafe { &*pac::ROSC::ptr() };
+        rosc.ctrl.modify(|_, w| w.enable().disable());
+
+        Self { sync: None }
+    }
+
+    /// Forget the sync point, as the RTC has been set.
+    pub fn resync(&mut self) {
+        self.sync = None;
+    }
+
+    pub fn sleep(&mut self, rtc: &mut RealTimeClock, scb: &mut SCB, uptime: &mut Uptime) {
+        let now = uptime.get_instant();
+        // The XOSC and the RTC oscillator drift apart while awake.
+        let sync = self.sync.filter(|&(synced_at, _)| {
+            matches!(now.checked_duration_since(synced_at), Some(age) if age.ticks() < SYNC_MAX_AGE_US)
+        });
+        let Some((synced_at, synced_second)) = sync else {
+            sleep_until_next_second(rtc, scb);
+            self.sync = Some((uptime.get_instant(), second_of_day(rtc)));
+            return;
+        };
+
+        let is_woken_by_rtc = dormant_until_next_second(rtc);
+
+        let second = second_of_day(rtc);
+        let seconds = (second + SECONDS_PER_DAY - synced_second) % SECONDS_PER_DAY;
+        uptime.catch_up(synced_at + Duration::from_ticks(seconds as u64 * 1_000_000));
+        if is_woken_by_rtc {
+            self.sync = Some((uptime.get_instant(), second));
+        }
+    }
+}
+
+/// Enter the SLEEP state until the next RTC second, with only the RTC and the TIMER clocked.
+fn sleep_until_next_second(rtc: &mut RealTimeClock, scb: &mut SCB) {
+    let now = rtc.now().unwrap();
+    rtc.schedule_alarm(DateTimeFilter::default().second((now.second + 1) % 60));
+
+    // Safety: the registers are only touched with the interrupts disabled.
+    let clocks = unsafe { &*pac::CLOCKS::ptr() };
+    let rtc_regs = unsafe { &*pac::RTC::ptr() };
+
+    interrupt::free(|_| {
+        let sleep_en = (
+            clocks.sleep_en0.read().bits(),
+            clocks.sleep_en1.read().bits(),
+        );
+        clocks.sleep_en0.write(|w| w.clk_rtc_rtc().set_bit());
+        // The TIMER counts the ticks generated by the watchdog.
+        clocks
+            .sleep_en1
+            .write(|w| w.clk_sys_timer().set_bit().clk_sys_watchdog().set_bit());
+
+        rtc_regs.inte.write(|w| w.rtc().set_bit());
+        unsafe { NVIC::unmask(pac::Interrupt::RTC_IRQ) };
+
+        scb.set_sleepdeep();
+        // A pending interrupt wakes the core up without being taken, as the interrupts are disabled.
+        cortex_m::asm::wfi();
+        scb.clear_sleepdeep();
+
+        NVIC::mask(pac::Interrupt::RTC_IRQ);
+        rtc_regs.inte.write(|w| w.rtc().clear_bit());
+        rtc.clear_interrupt();
+        NVIC::unpend(pac::Interrupt::RTC_IRQ);
+
+        clocks.sleep_en0.write(|w| unsafe { w.bits(sleep_en.0) });
+        clocks.sleep_en1.write(|w| unsafe { w.bits(sleep_en.1) });
+    });
+}
+
+/// Enter the DORMANT state until the next RTC second or a GPIO edge, return whether woken
+/// up by the RTC.
+fn dormant_until_next_second(rtc: &mut RealTimeClock) -> bool {
+    let now = rtc.now().unwrap();
+    rtc.schedule_alarm(DateTimeFilter::default().second((now.second + 1) % 60));
+
+    // Safety: the registers are only touched with the interrupts disabled.
+    let clocks = unsafe { &*pac::CLOCKS::ptr() };
+    let xosc = unsafe { &*pac::XOSC::ptr() };
+    let plls = unsafe { [&*pac::PLL_SYS::ptr(), &*pac::PLL_USB::ptr()] };
+    let io_bank0 = unsafe { &*pac::IO_BANK0::ptr() };
+    let sio = unsafe { &*pac::SIO::ptr() };
+    let rtc_regs = unsafe { &*pac::RTC::ptr() };
+
+    let pads_mask = GPIO_TCH.iter().fold(0, |acc, i| acc | 1 << i);
+    // The GPIO events as the bits of the `intr` and the `dormant_wake_inte` registers,
+    // which hold 8 GPIOs each.
+    let mut wake_events = [0u32; 4];
+    for (gpio, event) in GPIO_TCH
+        .iter()
+        .map(|&gpio| (gpio, GPIO_EDGE_LOW))
+        .chain([(GPIO_VBUS_DETECT, GPIO_EDGE_HIGH)])
+    {
+        wake_events[gpio as usize / 8] |= event << (gpio % 8 * 4);
+    }
+
+    interrupt::free(|_| {
+        rtc_regs.inte.write(|w| w.rtc().set_bit());
+
+        sio.gpio_oe_clr.write(|w| unsafe { w.bits(pads_mask) });
+        cortex_m::asm::delay(PAD_CHARGE_CYCLES);
+        for (i, &events) in wake_events.iter().enumerate() {
+            // The edges latched before, e.g. the pads being charged.
+            io_bank0.intr[i].write(|w| unsafe { w.bits(events) });
+            io_bank0.dormant_wake_inte[i].write(|w| unsafe { w.bits(events) });
+        }
+
+        // The system clock runs from the XOSC until the PLLs lock again after the wake-up.
+        clocks.clk_sys_ctrl.modify(|_, w| w.src().clk_ref());
+        while clocks.clk_sys_selected.read().bits() != CLK_SYS_SELECTED_REF {}
+        // Without the reference, the VCOs would keep running.
+        let pll_pwr = plls.map(|pll| pll.pwr.read().bits());
+        for pll in plls {
+            pll.pwr.modify(|_, w| {
+                w.pd().set_bit();
+                w.vcopd().set_bit();
+                w.postdivpd().set_bit()
+            });
+        }
+
+        xosc.dormant.write(|w| unsafe { w.bits(XOSC_DORMANT) });
+
+        while xosc.status.read().stable().bit_is_clear() {}
+        for pll in plls {
+            pll.pwr
+                .modify(|_, w| w.pd().clear_bit().vcopd().clear_bit());
+        }
+        for (pll, pwr) in plls.iter().zip(pll_pwr) {
+            while pll.cs.read().lock().bit_is_clear() {}
+            pll.pwr.write(|w| unsafe { w.bits(pwr) });
+        }
+        clocks
+            .clk_sys_ctrl
+            .modify(|_, w| w.src().clksrc_clk_sys_aux());
+        while clocks.clk_sys_selected.read().bits() != CLK_SYS_SELECTED_AUX {}
+
+        for (i, &events) in wake_events.iter().enumerate() {
+            io_bank0.dormant_wake_inte[i].write(|w| unsafe { w.bits(0) });
+            io_bank0.intr[i].write(|w| unsafe { w.bits(events) });
+        }
+        // Discharged, as left by the measurement.
+        sio.gpio_oe_set.write(|w| unsafe { w.bits(pads_mask) });
+
+        let is_woken_by_rtc = rtc_regs.intr.read().rtc().bit_is_set();
+        rtc_regs.inte.write(|w| w.rtc().clear_bit());
+        rtc.clear_interrupt();
+        NVIC::unpend(pac::Interrupt::RTC_IRQ);
+
+        is_woken_by_rtc
+    })
+}
+
+fn second_of_day(rtc: &RealTimeClock) -> u32 {
+    let now = rtc.now().unwrap();
+    now.hour as u32 * 3_600 + now.minute as u32 * 60 + now.second as u32
+}
+
+const SECONDS_PER_DAY: u32 = 24 * 3_600;
+// Up to 1 ms of drift, at 100 ppm between the oscillators.
+const SYNC_MAX_AGE_US: u64 = 10_000_000;
+// Three cycles of the RTC clock at 46.875 kHz, at the 125 MHz system clock.
+const CLK_RTC_STOP_CYCLES: u32 = 8_000;
+// Plenty for the pull-ups to charge the pads.
+const PAD_CHARGE_CYCLES: u32 = 1_000;
+const GPIO_EDGE_LOW: u32 = 1 << 2;
+const GPIO_EDGE_HIGH: u32 = 1 << 3;
+const CLK_SYS_SELECTED_REF: u32 = 1 << 0;
+const CLK_SYS_SELECTED_AUX: u32 = 1 << 1;
+// Stops the XOSC until an interrupt from a GPIO or the RTC.
+const XOSC_DORMANT: u32 = 0x636f_6d61;
@@ -0,0 +1,318 @@
+use cortex_m::interrupt;
+use rp_pico::hal::{
+    rom_data,
+    rtc::{DateTime, DayOfWeek},
+};
+
+use app_core::{
+    features::{
+        alarm::Alarm,
+        brightness::NightWindow,
+        display::{DateFormat, HourMode},
+    },
+    settings::Settings,
+};
+
+const XIP_BASE: u32 = 0x1000_0000;
+const FLASH_SIZE: u32 = 2048 * 1024;
+const SECTOR_SIZE: u32 = 4096;
+const PAGE_SIZE: usize = 256;
+
+/// The store occupies the last two flash sectors, which are excluded from the `FLASH` region in `memory.x`.
+const STORE_OFFSET: u32 = FLASH_SIZE - STORE_SECTOR_COUNT * SECTOR_SIZE;
+const STORE_SECTOR_COUNT: u32 = 2;
+
+const RECORD_SIZE: usize = 64;
+const RECORDS_PER_SECTOR: usize = SECTOR_SIZE as usize / RECORD_SIZE;
+// Bumped along with the record layout, the older records are ignored.
+const RECORD_MAGIC: u32 = 0x4b4c_4302;
+
+/// The data persisted across resets.
+pub struct Record {
+    /// The last committed wall-clock time.
+    pub datetime: DateTime,
+    pub settings: Settings,
+}
+
+/// A wear-levelled, append-only record store in the reserved flash sectors.
+///
+/// Records are appended to one sector until it is full, then the other sector is erased
+/// and the appending continues there. Each record carries a sequence number and a CRC,
+/// the valid record with the highest sequence number wins. An interrupted write leaves
+/// the previous record in place.
+pub struct SettingsStore {
+    /// The sector and the slot of the latest record along with its sequence number.
+    latest: Option<(u32, usize, u32)>,
+    boot2: [u32; 64],
+}
+
+impl SettingsStore {
+    pub fn new() -> Self {
+        let mut boot2 = [0u32; 64];
+        // The second stage bootloader is copied to RAM to restore the fast XIP mode after writing.
+        unsafe {
+            core::ptr::copy_nonoverlapping(XIP_BASE as *const u32, boot2.as_mut_ptr(), boot2.len());
+        }
+
+        let latest = (0..STORE_SECTOR_COUNT)
+            .flat_map(|sector| (0..RECORDS_PER_SECTOR).map(move |slot| (sector, slot)))
+            .filter_map(|(sector, slot)| {
+                decode(&read_slot(sector, slot)).map(|(seq, _)| (sector, slot, seq))
+            })
+            .max_by_key(|&(_, _, seq)| seq);
+
+        Self { latest, boot2 }
+    }
+
+    pub fn load(&self) -> Option<Record> {
+        self.latest
+            .and_then(|(sector, slot, _)| decode(&read_slot(sector, slot)))
+            .map(|(_, record)| record)
+    }
+
+    pub fn save(&mut self, record: &Record) {
+        let (sector, slot, seq) = match self.latest {
+            Some((sector, slot, seq)) if slot + 1 < RECORDS_PER_SECTOR => {
+                (sector, slot + 1, seq + 1)
+            }
+            Some((sector, _, seq)) => ((sector + 1) % STORE_SECTOR_COUNT, 0, seq + 1),
+            None => (0, 0, 0),
+        };
+
+        // Move on to the other sector when the slot holds garbage (e.g. old firmware),
+        // as erasing this one would wipe the latest record.
+        let (sector, slot) = if slot != 0 && read_slot(sector, slot).iter().any(|&b| b != 0xff) {
+            ((sector + 1) % STORE_SECTOR_COUNT, 0)
+        } else {
+            (sector, slot)
+        };
+        // Erase the sector when starting it over, it never holds the latest record then.
+        let erase = slot == 0;
+
+        let offset = STORE_OFFSET + sector * SECTOR_SIZE + (slot * RECORD_SIZE) as u32;
+        let page_offset = offset & !(PAGE_SIZE as u32 - 1);
+
+        // Programming can only clear bits, so the rest of the page is left intact by `0xff`.
+        let mut page = [0xffu8; PAGE_SIZE];
+        let record_offset = (offset - page_offset) as usize;
+        page[record_offset..record_offset + RECORD_SIZE].copy_from_slice(&encode(seq, record));
+
+        let sector_offset = erase.then_some(STORE_OFFSET + sector * SECTOR_SIZE);
+        interrupt::free(|_| unsafe {
+            flash_write(sector_offset, page_offset, &page, &self.boot2);
+        });
+
+        self.latest = Some((sector, slot, seq));
+    }
+}
+
+fn read_slot(sector: u32, slot: usize) -> [u8; RECORD_SIZE] {
+    let addr = XIP_BASE + STORE_OFFSET + sector * SECTOR_SIZE + (slot * RECORD_SIZE) as u32;
+    let mut bytes = [0u8; RECORD_SIZE];
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = unsafe { core::ptr::read_volatile((addr as *const u8).add(i)) };
+    }
+    bytes
+}
+
+/// Record layout (little-endian):
+///
+/// | Offset | Size | Field                                             |
+/// |--------|------|---------------------------------------------------|
+/// | 0      | 4    | magic                                             |
+/// | 4      | 4    | sequence number                                   |
+/// | 8      | 8    | year (2), month, day, day of week, hour, min, sec |
+/// | 16     | 8    | battery voltage gain (2 × `f32`)                  |
+/// | 24     | 1    | user settings flags, see below                    |
+/// | 25     | 2    | night window from and to hours, `0xff` is default |
+/// | 27     | 1    | reserved, `0xff`                                  |
+/// | 28     | 12   | alarms (4 × 3), see below                         |
+/// | 40     | 20   | reserved, `0xff`                                  |
+/// | 60     | 4    | CRC-32 of the bytes above                         |
+///
+/// The user settings flags are set when the corresponding setting has its default
+/// value, so that new settings can take over the reserved bits of the erased flash:
+///
+/// - bit 0: `date_format` is [`DateFormat::DayMonth`] (otherwise [`DateFormat::MonthDay`]),
+/// - bit 1: `show_date_periodically`,
+/// - bit 2: `hour_mode` is [`HourMode::H24`] (otherwise [`HourMode::H12`]).
+///
+/// An alarm is the hour, the minute and the weekdays with the bit 7 set when enabled.
+/// The hour is `0xff` for the default alarm.
+fn encode(seq: u32, record: &Record) -> [u8; RECORD_SIZE] {
+    let mut bytes = [0xffu8; RECORD_SIZE];
+
+    let dt = &record.datetime;
+    bytes[0..4].copy_from_slice(&RECORD_MAGIC.to_le_bytes());
+    bytes[4..8].copy_from_slice(&seq.to_le_bytes());
+    bytes[8..10].copy_from_slice(&dt.year.to_le_bytes());
+    bytes[10..16].copy_from_slice(&[
+        dt.month,
+        dt.day,
+        dt.day_of_week as u8,
+        dt.hour,
+        dt.minute,
+        dt.second,
+    ]);
+
+    let (g1, g2) = record.settings.bat_voltage_gain;
+    bytes[16..20].copy_from_slice(&g1.to_le_bytes());
+    bytes[20..24].copy_from_slice(&g2.to_le_bytes());
+
+    let settings = &record.settings;
+    if settings.date_format != DateFormat::DayMonth {
+        bytes[24] &= !(1 << 0);
+    }
+    if !settings.show_date_periodically {
+        bytes[24] &= !(1 << 1);
+    }
+    if settings.hour_mode != HourMode::H24 {
+        bytes[24] &= !(1 << 2);
+    }
+
+    if settings.night_window != NightWindow::default() {
+        bytes[25] = settings.night_window.from_hour;
+        bytes[26] = settings.night_window.to_hour;
+    }
+
+    for (i, alarm) in settings.alarms.iter().enumerate() {
+        if *alarm != Alarm::default() {
+            let offset = 28 + i * 3;
+            bytes[offset..offset + 3].copy_from_slice(&[
+                alarm.hour,
+                alarm.minute,
+                alarm.weekdays | (alarm.enabled as u8) << 7,
+            ]);
+        }
+    }
+
+    let crc = crc32(&bytes[0..60]);
+    bytes[60..64].copy_from_slice(&crc.to_le_bytes());
+
+    bytes
+}
+
+fn decode(bytes: &[u8; RECORD_SIZE]) -> Option<(u32, Record)> {
+    fn u32_at(bytes: &[u8], i: usize) -> u32 {
+        u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+    }
+
+    if u32_at(bytes, 0) != RECORD_MAGIC || u32_at(bytes, 60) != crc32(&bytes[0..60]) {
+        return None;
+    }
+
+    let day_of_week = day_of_week(bytes[12])?;
+
+    let record = Record {
+        datetime: DateTime {
+            year: u16::from_le_bytes([bytes[8], bytes[9]]),
+            month: bytes[10],
+            day: bytes[11],
+            day_of_week,
+            hour: bytes[13],
+            minute: bytes[14],
+            second: bytes[15],
+        },
+        settings: Settings {
+            bat_voltage_gain: (
+                f32::from_bits(u32_at(bytes, 16)),
+                f32::from_bits(u32_at(bytes, 20)),
+            ),
+            date_format: if bytes[24] & 1 << 0 != 0 {
+                DateFormat::DayMonth
+            } else {
+                DateFormat::MonthDay
+            },
+            show_date_periodically: bytes[24] & 1 << 1 != 0,
+            hour_mode: if bytes[24] & 1 << 2 != 0 {
+                HourMode::H24
+            } else {
+                HourMode::H12
+            },
+            night_window: if bytes[25] < 24 && bytes[26] < 24 {
+                NightWindow {
+                    from_hour: bytes[25],
+                    to_hour: bytes[26],
+                }
+            } else {
+                NightWindow::default()
+            },
+            alarms: core::array::from_fn(|i| match bytes[28 + i * 3..28 + i * 3 + 3] {
+                [hour, minute, weekdays] if hour < 24 && minute < 60 => Alarm {
+                    hour,
+                    minute,
+                    weekdays: weekdays & 0x7f,
+                    enabled: weekdays & 0x80 != 0,
+                },
+                _ => Alarm::default(),
+            }),
+        },
+    };
+
+    Some((u32_at(bytes, 4), record))
+}
+
+/// The RTC day of week, 0 is Sunday as in `app_core::state::RTC`.
+pub fn day_of_week(day_of_week: u8) -> Option<DayOfWeek> {
+    Some(match day_of_week {
+        0 => DayOfWeek::Sunday,
+        1 => DayOfWeek::Monday,
+        2 => DayOfWeek::Tuesday,
+        3 => DayOfWeek::Wednesday,
+        4 => DayOfWeek::Thursday,
+        5 => DayOfWeek::Friday,
+        6 => DayOfWeek::Saturday,
+        _ => return None,
+    })
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &b| {
+        (0..8).fold(crc ^ b as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+/// Optionally erase a sector, then program a page.
+///
+/// Must run from RAM, because XIP is not available while the flash is being written.
+/// The ROM function pointers are looked up in advance for the same reason.
+/// The interrupts must be disabled by the caller.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn flash_write(
+    sector_offset: Option<u32>,
+    page_offset: u32,
+    page: &[u8; PAGE_SIZE],
+    boot2: &[u32; 64],
+) {
+    let connect_internal_flash = rom_data::connect_internal_flash::ptr();
+    let flash_exit_xip = rom_data::flash_exit_xip::ptr();
+    let flash_range_erase = rom_data::flash_range_erase::ptr();
+    let flash_range_program = rom_data::flash_range_program::ptr();
+    let flash_flush_cache = rom_data::flash_flush_cache::ptr();
+    let boot2: extern "C" fn() = core::mem::transmute((boot2.as_ptr() as *const u8).add(1));
+
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+
+    connect_internal_flash();
+    flash_exit_xip();
+    if let Some(sector_offset) = sector_offset {
+        const BLOCK_SIZE: u32 = 1 << 16;
+        const BLOCK_ERASE_CMD: u8 = 0xd8;
+        flash_range_erase(
+            sector_offset,
+            SECTOR_SIZE as usize,
+            BLOCK_SIZE,
+            BLOCK_ERASE_CMD,
+        );
+    }
+    flash_range_program(page_offset, page.as_ptr(), PAGE_SIZE);
+    flash_flush_cache();
+    boot2();
+}
@@ -0,0 +1,56 @@
+use cortex_m::interrupt;
+use rp_pico::pac;
+
+use app_core::features::touch::TOUCH_PAD_COUNT;
+
+/// The GPIO pins connected to TCH_A, TCH_B and TCH_C.
+pub const GPIO_TCH: [u32; TOUCH_PAD_COUNT] = [14, 13, 15];
+
+/// The charge time of a single sample is capped at this many polling iterations.
+const SAMPLE_MAX: u16 = 1_000;
+const SAMPLE_COUNT: u16 = 16;
+
+pub fn touch_configure(io_bank0: &pac::IO_BANK0, pads_bank0: &pac::PADS_BANK0, sio: &pac::SIO) {
+    for i in GPIO_TCH {
+        const GPIO_FUNC_SIO: u8 = 5;
+        io_bank0.gpio[i as usize]
+            .gpio_ctrl
+            .write(|w| unsafe { w.funcsel().bits(GPIO_FUNC_SIO) });
+        pads_bank0.gpio[i as usize].modify(|_, w| {
+            w.ie().set_bit();
+            w.pue().set_bit();
+            w.pde().clear_bit();
+            w.schmitt().set_bit()
+        });
+    }
+    sio.gpio_out_clr
+        .write(|w| unsafe { w.bits(GPIO_TCH.iter().fold(0, |acc, i| acc | 1 << i)) });
+}
+
+/// Measure the time it takes the internal pull-up to charge the pad, in polling iterations.
+///
+/// A finger adds capacitance to the pad, so the charge time increases on touch.
+pub fn touch_measure(pad_index: usize, sio: &pac::SIO) -> u16 {
+    let mask = 1 << GPIO_TCH[pad_index];
+
+    let mut sum = 0;
+    for _ in 0..SAMPLE_COUNT {
+        // Discharge the pad (the output level is always low).
+        sio.gpio_oe_set.write(|w| unsafe { w.bits(mask) });
+        cortex_m::asm::delay(1_000);
+
+        sum += interrupt::free(|_| {
+            sio.gpio_oe_clr.write(|w| unsafe { w.bits(mask) });
+            let mut count = 0;
+            while sio.gpio_in.read().bits() & mask == 0 && count < SAMPLE_MAX {
+                count += 1;
+            }
+            count
+        });
+    }
+
+    // Leave the pad discharged to make the next measurement start from the same point.
+    sio.gpio_oe_set.write(|w| unsafe { w.bits(mask) });
+
+    sum
+}
@@ -1,145 +1,105 @@
-use cortex_m::{
-    interrupt,
-    peripheral::{syst::SystClkSource, SYST},
-};
-use cortex_m_rt::exception;
+use cortex_m::{interrupt, peripheral::NVIC};
+use rp_pico::pac;
 
-use app_core::common::Instant;
+use app_core::common::{Duration, Instant};
 
+/// A microsecond clock backed by the 64-bit TIMER, which keeps counting in the SLEEP state.
+///
+/// The TIMER stops in the DORMANT state, the time spent in it is made up for by
+/// [`Self::catch_up()`]. Blocking delays wait for interrupts (WFI), woken up by the TIMER alarm 0.
 pub struct Uptime {
-    _syst: SYST,
-    syst_reload_period_ms: u32,
+    timer: pac::TIMER,
+    /// The time the TIMER has missed, in microseconds.
+    offset: u64,
 }
 
 impl Uptime {
-    /// Blocking delays longer than SYST reload period will invoke WFI (wait for interrupt) instruction.
-    pub fn new(mut syst: SYST, syst_reload_period_ms: u32) -> Self {
-        syst.set_clock_source(SystClkSource::External);
-
-        // See
-        //
-        // - [SysTick Reload Value Register](https://developer.arm.com/documentation/dui0497/a/cortex-m0-peripherals/optional-system-timer--systick/systick-reload-value-register)
-        // - [SysTick Calibration Value Register](https://developer.arm.com/documentation/dui0497/a/cortex-m0-peripherals/optional-system-timer--systick/systick-calibration-value-register)
-
-        let ticks_per_reload_period =
-            syst_reload_period_ms * (SYST::get_ticks_per_10ms() + 1) / 10 - 1;
-
-        syst.set_reload(ticks_per_reload_period);
-        syst.clear_current();
-        syst.enable_counter();
-        syst.enable_interrupt();
-
-        Uptime {
-            _syst: syst,
-            syst_reload_period_ms,
-        }
+    pub fn new(timer: pac::TIMER, resets: &mut pac::RESETS) -> Self {
+        resets.reset.modify(|_, w| w.timer().clear_bit());
+        while resets.reset_done.read().timer().bit_is_clear() {}
+
+        timer.inte.write(|w| w.alarm_0().set_bit());
+
+        Uptime { timer, offset: 0 }
     }
 
     /// Return the "uptime" in microseconds.
     pub fn get_us(&self) -> u64 {
-        /// Return the uptime, if [`SYST::get_current()`] and [`SYST_RELOAD_COUNT`]
-        /// are captured within the same [`SYST`] run.
-        ///
-        /// A [`SYST`] overflow occurring between capturing [`SYST::get_current()`]
-        /// and [`SYST_RELOAD_COUNT`] would result in a large error in the calculated
-        /// uptime.
-        fn try_get_us() -> Option<u64> {
-            let syst_current1 = SYST::get_current();
-            interrupt::free(|_| {
-                let syst_current2 = SYST::get_current();
-                if syst_current2 <= syst_current1 {
-                    let syst_reload_count = unsafe { SYST_RELOAD_COUNT };
-
-                    let syst_reload = SYST::get_reload();
-
-                    let syst_ticks_per_10ms = (SYST::get_ticks_per_10ms() + 1) as u64;
-
-                    let syst_base_us = {
-                        let syst_reload_period_us =
-                            1000 * (syst_reload as u64 + 1) * 10 / syst_ticks_per_10ms;
-
-                        syst_reload_count * syst_reload_period_us
-                    };
-
-                    let syst_current_us =
-                        1000 * (syst_reload - syst_current1) as u64 * 10 / syst_ticks_per_10ms;
-
-                    Some(syst_base_us + syst_current_us)
-                } else {
-                    None
-                }
-            })
-        }
-
-        loop {
-            if let Some(uptime) = try_get_us() {
-                return uptime;
-            }
-        }
+        self.get_timer_us() + self.offset
     }
 
     pub fn get_instant(&self) -> Instant {
         Instant::from_ticks(self.get_us())
     }
 
-    pub fn delay_us(&self, us: u64) {
-        let start = self.get_instant();
-        let delay = fugit::Duration::<u64, 1, 1_000_000>::from_ticks(us);
-        let end = start
-            .checked_add_duration(delay)
-            .expect("uptime must not overflow during the delay");
+    /// Move the uptime forward to `now`, after the TIMER has been stopped. The uptime
+    /// never goes back, an earlier `now` is ignored.
+    pub fn catch_up(&mut self, now: Instant) {
+        self.offset += now.ticks().saturating_sub(self.get_us());
+    }
+
+    fn get_timer_us(&self) -> u64 {
+        // The raw registers don't latch, so the high word is read twice to catch a carry.
         loop {
-            let now = self.get_instant();
-            if let Some(left) = end.checked_duration_since(now) {
-                if left.ticks() == 0 {
-                    break;
-                } else if left.to_millis() >= self.syst_reload_period_ms as u64 {
-                    cortex_m::asm::wfi();
-                }
-            } else {
-                // let overshot = now - end;
-                // if overshot > Duration::from_ticks(20u64) {
-                //     rprintln!("Delay overshot by {}", overshot);
-                // }
-                break;
+            let hi = self.timer.timerawh.read().bits();
+            let lo = self.timer.timerawl.read().bits();
+            if self.timer.timerawh.read().bits() == hi {
+                return (hi as u64) << 32 | lo as u64;
             }
         }
     }
 
+    pub fn delay_us(&self, us: u64) {
+        self.sleep_until(
+            self.get_instant()
+                .checked_add_duration(Duration::from_ticks(us))
+                .expect("uptime must not overflow during the delay"),
+        );
+    }
+
     pub fn delay_ms(&self, ms: u64) {
-        let start = self.get_instant();
         let delay = fugit::Duration::<u64, 1, 1_000>::from_ticks(ms);
-        let end = start
-            .checked_add_duration(delay)
-            .expect("uptime must not overflow during the delay");
+        self.sleep_until(
+            self.get_instant()
+                .checked_add_duration(delay)
+                .expect("uptime must not overflow during the delay"),
+        );
+    }
+
+    /// Block until the given instant, waiting for interrupts (WFI) in between.
+    pub fn sleep_until(&self, wake_at: Instant) {
         loop {
             let now = self.get_instant();
-            if let Some(left) = end.checked_duration_since(now) {
-                if left.to_micros() == 0 {
-                    break;
-                } else if left.to_millis() >= self.syst_reload_period_ms as u64 {
-                    cortex_m::asm::wfi();
-                }
-            } else {
-                // let overshot = now - end;
-                // if overshot > Duration::from_ticks(20u64) {
-                //     rprintln!("Delay overshot by {}", overshot);
-                // }
+            let Some(left) = wake_at.checked_duration_since(now) else {
                 break;
+            };
+            if left.ticks() < MIN_SLEEP_US {
+                continue;
             }
-        }
-    }
-}
 
-#[exception]
-#[allow(non_snake_case)]
-fn SysTick() {
-    unsafe {
-        SYST_RELOAD_COUNT += 1;
+            // The alarm only compares the lower 32 bits, the longer delays take several alarms.
+            let alarm_at = now + left.min(Duration::from_ticks(MAX_ALARM_US));
+
+            interrupt::free(|_| {
+                self.timer.alarm0.write(|w| unsafe {
+                    w.bits(alarm_at.ticks().wrapping_sub(self.offset) as u32)
+                });
+                // The alarm never fires if the time has passed while arming it.
+                if self.get_instant() < alarm_at {
+                    unsafe { NVIC::unmask(pac::Interrupt::TIMER_IRQ_0) };
+                    // A pending interrupt wakes the core up without being taken, as the interrupts are disabled.
+                    cortex_m::asm::wfi();
+                    NVIC::mask(pac::Interrupt::TIMER_IRQ_0);
+                }
+
+                self.timer.armed.write(|w| unsafe { w.bits(1 << 0) });
+                self.timer.intr.write(|w| w.alarm_0().set_bit());
+                NVIC::unpend(pac::Interrupt::TIMER_IRQ_0);
+            });
+        }
     }
 }
 
-/// The uptime overflows after [`SYST_RELOAD_COUNT`] times `syst_reload_period_ms`.
-///
-/// For `syst_reload_period_ms` less than 1 second this variable should probably be `u64`.
-static mut SYST_RELOAD_COUNT: u64 = 0;
+// Shorter delays are busy-waited, as arming the alarm would take about as long.
+const MIN_SLEEP_US: u64 = 10;
+const MAX_ALARM_US: u64 = 1 << 31;
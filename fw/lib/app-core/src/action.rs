@@ -1,6 +1,22 @@
-use crate::features::charger::ChargerAction;
+use crate::{
+    features::{
+        alarm::AlarmAction, charger::ChargerAction, display::DIGIT_COUNT, power::PowerAction,
+        set_time::ClockAction, sound::SoundAction,
+    },
+    settings::Settings,
+};
 
 pub enum Action {
     Display(seg_disp::disp::Action),
+    /// Replace the frame refreshed by the display driver on its own, see [`Refresh::Streamed`].
+    ///
+    /// [`Refresh::Streamed`]: crate::features::display::Refresh::Streamed
+    DisplayFrame(seg_disp::disp::Frame<DIGIT_COUNT>),
     Battery(ChargerAction),
+    Clock(ClockAction),
+    /// Persist the settings.
+    SaveSettings(Settings),
+    Alarm(AlarmAction),
+    Sound(SoundAction),
+    Power(PowerAction),
 }
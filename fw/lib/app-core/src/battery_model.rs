@@ -0,0 +1,212 @@
+//! A NiMH battery model, to feed `state.bat_voltage` on the host.
+//!
+//! Crude, but it walks the [`Charger`](crate::features::charger::Charger) through its
+//! states the way real cells would.
+
+use crate::common::Duration;
+
+/// The cell parameters, the defaults are a 2000 mAh AA cell.
+#[derive(Copy, Clone, Debug)]
+pub struct CellParams {
+    pub capacity_ah: f32,
+    pub internal_resistance_ohm: f32,
+    pub ambient_c: f32,
+    /// The temperature rise per watt turned into heat, once settled.
+    pub thermal_resistance_k_per_w: f32,
+    /// How fast the temperature settles.
+    pub thermal_time_constant: Duration,
+    /// Negative, the voltage falls as the cell heats up. This is what makes the −ΔV bump
+    /// at full charge, when the charge current turns into heat.
+    pub temperature_coefficient_v_per_k: f32,
+    /// The state of charge above which some of the charge current turns into heat.
+    pub charge_acceptance_soc: f32,
+    /// The fraction of the charge lost per day.
+    pub self_discharge_per_day: f32,
+}
+
+impl Default for CellParams {
+    fn default() -> Self {
+        Self {
+            capacity_ah: 2.0,
+            internal_resistance_ohm: 0.1,
+            ambient_c: 25.0,
+            thermal_resistance_k_per_w: 60.0,
+            thermal_time_constant: Duration::from_ticks(20 * 60 * 1_000_000),
+            temperature_coefficient_v_per_k: -0.004,
+            charge_acceptance_soc: 0.9,
+            self_discharge_per_day: 0.01,
+        }
+    }
+}
+
+pub struct Cell {
+    pub params: CellParams,
+    charge_ah: f32,
+    temperature_c: f32,
+}
+
+impl Cell {
+    /// A cell at the ambient temperature and the given state of charge, `0.0..=1.0`.
+    pub fn new(params: CellParams, soc: f32) -> Self {
+        Self {
+            params,
+            charge_ah: params.capacity_ah * soc.clamp(0.0, 1.0),
+            temperature_c: params.ambient_c,
+        }
+    }
+
+    pub fn soc(&self) -> f32 {
+        self.charge_ah / self.params.capacity_ah
+    }
+
+    pub fn temperature_c(&self) -> f32 {
+        self.temperature_c
+    }
+
+    /// The terminal voltage under the current, positive when charging.
+    pub fn voltage(&self, current_a: f32) -> f32 {
+        let params = &self.params;
+        open_circuit_voltage(self.soc())
+            + current_a * params.internal_resistance_ohm
+            + (self.temperature_c - params.ambient_c) * params.temperature_coefficient_v_per_k
+    }
+
+    /// Pass the current (positive when charging) through the cell for the duration.
+    pub fn step(&mut self, current_a: f32, duration: Duration) {
+        // Short enough steps for the temperature to settle smoothly.
+        let max_step = self.params.thermal_time_constant / 10;
+        let mut remaining = duration;
+        while remaining.ticks() > 0 {
+            let step = remaining.min(max_step);
+            self.step_by(current_a, step.ticks() as f32 / 3_600_000_000.0);
+            remaining -= step;
+        }
+    }
+
+    fn step_by(&mut self, current_a: f32, hours: f32) {
+        let params = self.params;
+
+        let efficiency = self.charge_efficiency(current_a);
+        let self_discharge_ah = self.charge_ah * params.self_discharge_per_day * hours / 24.0;
+        self.charge_ah = (self.charge_ah + current_a * efficiency * hours - self_discharge_ah)
+            .clamp(0.0, params.capacity_ah);
+
+        let heat_w = current_a * current_a * params.internal_resistance_ohm
+            + current_a.max(0.0) * (1.0 - efficiency) * self.voltage(current_a);
+        let settled_c = params.ambient_c + heat_w * params.thermal_resistance_k_per_w;
+        let time_constant_h = params.thermal_time_constant.ticks() as f32 / 3_600_000_000.0;
+        self.temperature_c += (settled_c - self.temperature_c) * (hours / time_constant_h).min(1.0);
+    }
+
+    /// The fraction of the charge current stored, the rest turns into heat.
+    fn charge_efficiency(&self, current_a: f32) -> f32 {
+        let acceptance = self.params.charge_acceptance_soc;
+        if current_a <= 0.0 || self.soc() <= acceptance {
+            1.0
+        } else {
+            ((1.0 - self.soc()) / (1.0 - acceptance)).clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Two cells in series, as measured by the app.
+pub struct Battery {
+    pub cells: [Cell; 2],
+}
+
+impl Battery {
+    pub fn new(cells: [Cell; 2]) -> Self {
+        Self { cells }
+    }
+
+    /// Pass the current (positive when charging) through the cells for the duration.
+    pub fn step(&mut self, current_a: f32, duration: Duration) {
+        for cell in &mut self.cells {
+            cell.step(current_a, duration);
+        }
+    }
+
+    /// The cell voltages under the current, as measured by the app: the ADC reads the
+    /// first cell and the whole battery, saturating at its reference voltage.
+    pub fn bat_voltage(&self, current_a: f32) -> (f32, f32) {
+        let v1 = self.cells[0].voltage(current_a).min(ADC_VREF);
+        let total = (v1 + self.cells[1].voltage(current_a)).min(ADC_VREF);
+
+        (v1, total - v1)
+    }
+}
+
+/// Interpolate the resting voltage at the state of charge.
+fn open_circuit_voltage(soc: f32) -> f32 {
+    OCV_CURVE
+        .windows(2)
+        .find(|pair| soc <= pair[1].0)
+        .map(|pair| {
+            let ((soc0, v0), (soc1, v1)) = (pair[0], pair[1]);
+            v0 + (v1 - v0) * (soc - soc0) / (soc1 - soc0)
+        })
+        .unwrap_or(OCV_CURVE[OCV_CURVE.len() - 1].1)
+}
+
+const ADC_VREF: f32 = 3.0;
+// (state of charge, open-circuit voltage)
+const OCV_CURVE: [(f32, f32); 7] = [
+    (0.0, 1.00),
+    (0.05, 1.15),
+    (0.2, 1.22),
+    (0.5, 1.26),
+    (0.8, 1.30),
+    (0.95, 1.38),
+    (1.0, 1.40),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_ticks(3_600_000_000);
+
+    #[test]
+    fn cell_test() {
+        let mut cell = Cell::new(CellParams::default(), 0.5);
+        let resting = cell.voltage(0.0);
+        assert!(cell.voltage(0.2) > resting);
+        assert!(cell.voltage(-0.2) < resting);
+
+        // 0.1C for 4h, barely warm below the charge acceptance level.
+        cell.step(0.2, 4 * HOUR);
+        assert!((cell.soc() - 0.9).abs() < 0.005);
+        assert!(cell.temperature_c() - 25.0 < 0.5);
+
+        // Overcharged, the temperature rises and the voltage falls.
+        cell.step(0.2, HOUR / 2);
+        let peak = cell.voltage(0.2);
+        cell.step(0.2, 3 * HOUR);
+        assert!(cell.soc() > 0.99);
+        assert!(cell.temperature_c() > 35.0);
+        assert!(cell.voltage(0.2) < peak - 0.02);
+
+        // Cooled down and slowly self-discharging.
+        let soc = cell.soc();
+        cell.step(0.0, 24 * HOUR);
+        assert!(cell.temperature_c() - 25.0 < 0.1);
+        assert!((cell.soc() / soc - 0.99).abs() < 0.001);
+    }
+
+    #[test]
+    fn adc_saturation_test() {
+        let battery = Battery::new([
+            Cell::new(CellParams::default(), 1.0),
+            Cell::new(
+                CellParams {
+                    internal_resistance_ohm: 1.0,
+                    ..Default::default()
+                },
+                1.0,
+            ),
+        ]);
+        let (v1, v2) = battery.bat_voltage(0.2);
+        assert!((v1 - 1.42).abs() < 0.001);
+        assert!((v1 + v2 - ADC_VREF).abs() < 0.001);
+    }
+}
@@ -0,0 +1,48 @@
+/// The number of days in the month, 1..=12, of the Gregorian calendar.
+pub fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 => match (year % 4, year % 100, year % 400) {
+            (_, _, 0) => 29,
+            (_, 0, _) => 28,
+            (0, _, _) => 29,
+            _ => 28,
+        },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The day of week of the date, 0 is Sunday as in [`RTC::day_of_week`].
+///
+/// [`RTC::day_of_week`]: crate::state::RTC::day_of_week
+pub fn day_of_week(year: u16, month: u8, day: u8) -> u8 {
+    // Sakamoto's method, the year starting in March.
+    const MONTH_OFFSETS: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let year = if month < 3 { year - 1 } else { year };
+    ((year + year / 4 - year / 100 + year / 400 + MONTH_OFFSETS[month as usize - 1] + day as u16)
+        % 7) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_in_month_test() {
+        assert_eq!(days_in_month(2023, 4), 30);
+        assert_eq!(days_in_month(2023, 12), 31);
+        assert_eq!(days_in_month(2023, 2), 28);
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+    }
+
+    #[test]
+    fn day_of_week_test() {
+        assert_eq!(day_of_week(2023, 4, 7), 5);
+        assert_eq!(day_of_week(2000, 1, 1), 6);
+        assert_eq!(day_of_week(2000, 2, 29), 2);
+        assert_eq!(day_of_week(2024, 3, 1), 5);
+        assert_eq!(day_of_week(2099, 12, 31), 4);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{features::touch::ButtonEvent, task::Topics};
+
+/// What a feature tells the others about, see [`Events`].
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Event {
+    Button(ButtonEvent),
+    /// The external power has been connected (`true`) or lost (`false`).
+    ExtPower(bool),
+    /// The alarm with the given index has started ringing.
+    AlarmFired(usize),
+}
+
+impl Event {
+    pub const BUTTON: Topics = Topics::topic(0);
+    pub const EXT_POWER: Topics = Topics::topic(1);
+    pub const ALARM_FIRED: Topics = Topics::topic(2);
+
+    pub fn topic(&self) -> Topics {
+        match self {
+            Event::Button(_) => Self::BUTTON,
+            Event::ExtPower(_) => Self::EXT_POWER,
+            Event::AlarmFired(_) => Self::ALARM_FIRED,
+        }
+    }
+}
+
+/// The events published by the features, read by the others through an [`EventsCursor`].
+///
+/// The topics published since the last [`Self::take_published()`] are passed on to
+/// the scheduler, which wakes up the tasks subscribed to them.
+#[derive(Default)]
+pub struct Events {
+    queue: Queue<Event, EVENTS_CAPACITY>,
+    published: Topics,
+}
+
+pub type EventsCursor = Cursor;
+
+impl Events {
+    pub fn publish(&mut self, event: Event) {
+        self.published = self.published | event.topic();
+        self.queue.push(event);
+    }
+
+    /// Return the next event not yet seen through the cursor.
+    pub fn read(&self, cursor: &mut EventsCursor) -> Option<Event> {
+        self.queue.read(cursor)
+    }
+
+    /// Return the topics published since the last call.
+    pub fn take_published(&mut self) -> Topics {
+        core::mem::take(&mut self.published)
+    }
+}
+
+const EVENTS_CAPACITY: usize = 8;
+
+/// A fixed-capacity broadcast queue.
+///
+/// Every consumer reads the items at its own pace through a [`Cursor`].
+/// When the queue is full, the oldest item is overwritten and the consumers lagging
+/// behind skip it.
+pub struct Queue<T, const N: usize> {
+    items: [Option<T>; N],
+    /// The sequence number of the next item.
+    next: u32,
+}
+
+/// A position in a [`Queue`] of a particular consumer.
+#[derive(Default)]
+pub struct Cursor {
+    next: u32,
+}
+
+impl<T: Copy, const N: usize> Default for Queue<T, N> {
+    fn default() -> Self {
+        Self {
+            items: [None; N],
+            next: 0,
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> Queue<T, N> {
+    pub fn push(&mut self, item: T) {
+        self.items[self.next as usize % N] = Some(item);
+        self.next += 1;
+    }
+
+    /// Return the next item not yet seen through the cursor.
+    pub fn read(&self, cursor: &mut Cursor) -> Option<T> {
+        let oldest = self.next.saturating_sub(N as u32);
+        cursor.next = cursor.next.max(oldest);
+
+        if cursor.next < self.next {
+            let item = self.items[cursor.next as usize % N];
+            cursor.next += 1;
+            item
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::features::touch::Button;
+
+    use super::*;
+
+    #[test]
+    fn events_test() {
+        let mut events = Events::default();
+        let mut cursor = EventsCursor::default();
+
+        events.publish(Event::ExtPower(false));
+        events.publish(Event::Button(ButtonEvent::Press(Button::A)));
+
+        assert_eq!(events.take_published(), Event::EXT_POWER | Event::BUTTON);
+        assert_eq!(events.take_published(), Topics::NONE);
+        assert_eq!(events.read(&mut cursor), Some(Event::ExtPower(false)));
+        assert_eq!(
+            events.read(&mut cursor),
+            Some(Event::Button(ButtonEvent::Press(Button::A)))
+        );
+        assert_eq!(events.read(&mut cursor), None);
+    }
+
+    #[test]
+    fn queue_test() {
+        let mut queue = Queue::<u32, 4>::default();
+        let mut cursor1 = Cursor::default();
+        let mut cursor2 = Cursor::default();
+
+        queue.push(0);
+        assert_eq!(queue.read(&mut cursor1), Some(0));
+        assert_eq!(queue.read(&mut cursor1), None);
+
+        for i in 1..=4 {
+            queue.push(i);
+        }
+
+        // The lagging cursor skips the overwritten item.
+        let items = core::iter::from_fn(|| queue.read(&mut cursor2)).collect::<Vec<_>>();
+        assert_eq!(items, [1, 2, 3, 4]);
+
+        let items = core::iter::from_fn(|| queue.read(&mut cursor1)).count();
+        assert_eq!(items, 4);
+    }
+}
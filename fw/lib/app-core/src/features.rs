@@ -1,2 +1,11 @@
+pub mod alarm;
+pub mod brightness;
 pub mod charger;
+pub mod date_view;
 pub mod display;
+pub mod power;
+pub mod set_alarm;
+pub mod set_time;
+pub mod sound;
+pub mod stopwatch;
+pub mod touch;
@@ -0,0 +1,318 @@
+use crate::{
+    action::Action,
+    common::Duration,
+    event::{Event, EventsCursor},
+    state::{State, RTC},
+    task::{NextRun, Task, Topics},
+};
+
+use super::{
+    stopwatch::Stopwatch,
+    touch::{Button, ButtonEvent},
+};
+
+pub const ALARM_COUNT: usize = 4;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Alarm {
+    pub hour: u8,
+    pub minute: u8,
+    /// The days of week the alarm is active on, bit 0 is Sunday.
+    pub weekdays: u8,
+    pub enabled: bool,
+}
+
+impl Default for Alarm {
+    fn default() -> Self {
+        Self {
+            hour: 7,
+            minute: 0,
+            weekdays: WEEKDAYS_MON_FRI,
+            enabled: false,
+        }
+    }
+}
+
+impl Alarm {
+    fn is_due(&self, rtc: &RTC) -> bool {
+        self.enabled
+            && self.hour == rtc.hour
+            && self.minute == rtc.minute
+            && self.weekdays & 1 << rtc.day_of_week != 0
+    }
+}
+
+pub const WEEKDAYS_MON_FRI: u8 = 0b0111110;
+pub const WEEKDAYS_ALL: u8 = 0b1111111;
+pub const WEEKDAYS_WEEKEND: u8 = 0b1000001;
+
+pub enum AlarmAction {
+    /// Start ringing the alarm with the given index.
+    Ring(usize),
+    Stop,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum AlarmState {
+    Idle,
+    Ringing {
+        index: usize,
+        /// The flashing phase of the display.
+        is_visible: bool,
+    },
+    Snoozed {
+        index: usize,
+        /// The minute of day to ring again at.
+        until: u16,
+    },
+}
+
+impl AlarmState {
+    pub fn is_ringing(&self) -> bool {
+        matches!(self, AlarmState::Ringing { .. })
+    }
+}
+
+/// Rings the alarms in `state.settings.alarms`, see [`SetAlarm`] for setting them.
+///
+/// [`SetAlarm`]: super::set_alarm::SetAlarm
+///
+/// An alarm is checked once a minute, when the RTC minute changes. A ringing alarm
+/// is snoozed for [`SNOOZE_MINUTES`] by releasing [`Button::B`] or [`Button::C`] and
+/// dismissed by releasing [`Button::A`]. Acting on the release makes sure a long press
+/// is not picked up by other features after the alarm has stopped. An alarm ringing
+/// for [`RING_TIMEOUT_MS`] stops by itself.
+#[derive(Default)]
+pub struct AlarmClock {
+    events: EventsCursor,
+    /// The minute of day the alarms were last checked at.
+    last_checked: Option<u16>,
+    /// The time since the alarm started ringing.
+    ringing: Stopwatch,
+}
+
+impl Task<State, Action> for AlarmClock {
+    fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        let mut action = None;
+
+        while let Some(event) = state.events.read(&mut self.events) {
+            let Event::Button(event) = event else {
+                continue;
+            };
+            if let AlarmState::Ringing { index, .. } = state.alarm {
+                match event {
+                    ButtonEvent::Release(Button::A) => {
+                        state.alarm = AlarmState::Idle;
+                        action = Some(AlarmAction::Stop);
+                    }
+                    ButtonEvent::Release(Button::B | Button::C) => {
+                        state.alarm = AlarmState::Snoozed {
+                            index,
+                            until: (minute_of_day(&state.rtc) + SNOOZE_MINUTES) % MINUTES_PER_DAY,
+                        };
+                        action = Some(AlarmAction::Stop);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let now = minute_of_day(&state.rtc);
+        if self.last_checked != Some(now) {
+            self.last_checked = Some(now);
+
+            let due = match state.alarm {
+                AlarmState::Snoozed { index, until } if until == now => Some(index),
+                AlarmState::Ringing { .. } => None,
+                _ => state
+                    .settings
+                    .alarms
+                    .iter()
+                    .position(|alarm| alarm.is_due(&state.rtc)),
+            };
+            if let Some(index) = due {
+                state.alarm = AlarmState::Ringing {
+                    index,
+                    is_visible: true,
+                };
+                self.ringing.start(state.now);
+                state.events.publish(Event::AlarmFired(index));
+                action = Some(AlarmAction::Ring(index));
+            }
+        }
+
+        if let AlarmState::Ringing { is_visible, .. } = &mut state.alarm {
+            if self
+                .ringing
+                .has_passed(state.now, Duration::from_ticks(RING_TIMEOUT_MS * 1_000))
+            {
+                state.alarm = AlarmState::Idle;
+                action = Some(AlarmAction::Stop);
+            } else {
+                *is_visible = self
+                    .ringing
+                    .is_blink_visible(state.now, Duration::from_ticks(ALARM_PERIOD_US));
+            }
+        }
+
+        (
+            action.map(Action::Alarm),
+            NextRun::After(Duration::from_ticks(ALARM_PERIOD_US)),
+        )
+    }
+
+    fn subscriptions(&self) -> Topics {
+        Event::BUTTON
+    }
+}
+
+fn minute_of_day(rtc: &RTC) -> u16 {
+    rtc.hour as u16 * 60 + rtc.minute as u16
+}
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+pub const SNOOZE_MINUTES: u16 = 9;
+// Also the display flashing half-period.
+const ALARM_PERIOD_US: u64 = 250_000;
+pub const RING_TIMEOUT_MS: u64 = 10 * 60_000;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Advance the simulated clock by one minute, running the alarm clock every [`ALARM_PERIOD_US`].
+    fn run_minute(alarm_clock: &mut AlarmClock, state: &mut State) -> Vec<AlarmAction> {
+        const RUNS_PER_MINUTE: u64 = 60_000_000 / ALARM_PERIOD_US;
+
+        let mut actions = Vec::new();
+        for i in 0..RUNS_PER_MINUTE {
+            state.rtc.second = (i * 60 / RUNS_PER_MINUTE) as u8;
+            if let (Some(Action::Alarm(action)), _) = alarm_clock.run(state) {
+                actions.push(action);
+            }
+            state.now += Duration::from_ticks(ALARM_PERIOD_US);
+        }
+        state.rtc.second = 0;
+        state.rtc.minute += 1;
+        if state.rtc.minute == 60 {
+            state.rtc.minute = 0;
+            state.rtc.hour = (state.rtc.hour + 1) % 24;
+            if state.rtc.hour == 0 {
+                state.rtc.day_of_week = (state.rtc.day_of_week + 1) % 7;
+            }
+        }
+        actions
+    }
+
+    fn state_at(day_of_week: u8, hour: u8, minute: u8) -> State {
+        let mut state = State::default();
+        state.rtc.day_of_week = day_of_week;
+        state.rtc.hour = hour;
+        state.rtc.minute = minute;
+        state.settings.alarms[1] = Alarm {
+            hour: 7,
+            minute: 0,
+            weekdays: WEEKDAYS_MON_FRI,
+            enabled: true,
+        };
+        state
+    }
+
+    #[test]
+    fn ring_dismiss_test() {
+        let mut alarm_clock = AlarmClock::default();
+        let mut state = state_at(1, 6, 59);
+
+        assert!(run_minute(&mut alarm_clock, &mut state).is_empty());
+        assert!(matches!(
+            run_minute(&mut alarm_clock, &mut state)[..],
+            [AlarmAction::Ring(1)]
+        ));
+        assert!(state.alarm.is_ringing());
+        assert_eq!(state.events.take_published(), Event::ALARM_FIRED);
+
+        state
+            .events
+            .publish(Event::Button(ButtonEvent::Press(Button::A)));
+        state
+            .events
+            .publish(Event::Button(ButtonEvent::Release(Button::A)));
+        assert!(matches!(
+            run_minute(&mut alarm_clock, &mut state)[..],
+            [AlarmAction::Stop]
+        ));
+        assert_eq!(state.alarm, AlarmState::Idle);
+    }
+
+    #[test]
+    fn snooze_test() {
+        let mut alarm_clock = AlarmClock::default();
+        let mut state = state_at(5, 7, 0);
+
+        run_minute(&mut alarm_clock, &mut state);
+        state
+            .events
+            .publish(Event::Button(ButtonEvent::Release(Button::C)));
+
+        let actions = (0..SNOOZE_MINUTES)
+            .flat_map(|_| run_minute(&mut alarm_clock, &mut state))
+            .collect::<Vec<_>>();
+        assert!(matches!(actions[..], [AlarmAction::Stop]));
+        assert!(matches!(state.alarm, AlarmState::Snoozed { index: 1, .. }));
+
+        assert!(matches!(
+            run_minute(&mut alarm_clock, &mut state)[..],
+            [AlarmAction::Ring(1)]
+        ));
+    }
+
+    #[test]
+    fn weekdays_timeout_test() {
+        // Saturday.
+        let mut alarm_clock = AlarmClock::default();
+        let mut state = state_at(6, 7, 0);
+        assert!(run_minute(&mut alarm_clock, &mut state).is_empty());
+
+        // Friday, nobody around.
+        let mut alarm_clock = AlarmClock::default();
+        let mut state = state_at(5, 7, 0);
+        let actions = (0..RING_TIMEOUT_MS / 60_000 + 1)
+            .flat_map(|_| run_minute(&mut alarm_clock, &mut state))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            actions[..],
+            [AlarmAction::Ring(1), AlarmAction::Stop]
+        ));
+        assert_eq!(state.alarm, AlarmState::Idle);
+    }
+
+    #[test]
+    fn flash_test() {
+        let mut alarm_clock = AlarmClock::default();
+        let mut state = state_at(1, 7, 0);
+        let is_visible = |state: &State| match state.alarm {
+            AlarmState::Ringing { is_visible, .. } => Some(is_visible),
+            _ => None,
+        };
+
+        alarm_clock.run(&mut state);
+        assert_eq!(is_visible(&state), Some(true));
+
+        // Woken up by the button events, without the time moving on.
+        for _ in 0..1_000 {
+            alarm_clock.run(&mut state);
+        }
+        assert_eq!(is_visible(&state), Some(true));
+
+        state.now += Duration::from_ticks(ALARM_PERIOD_US);
+        alarm_clock.run(&mut state);
+        assert_eq!(is_visible(&state), Some(false));
+
+        state.now += Duration::from_ticks(RING_TIMEOUT_MS * 1_000);
+        assert!(matches!(
+            alarm_clock.run(&mut state),
+            (Some(Action::Alarm(AlarmAction::Stop)), _)
+        ));
+        assert_eq!(state.alarm, AlarmState::Idle);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::state::State;
+
+use super::charger::BatteryState;
+
+/// The hours of day the display is dimmed between.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct NightWindow {
+    /// The first hour of the night, `0..24`.
+    pub from_hour: u8,
+    /// The first hour of the day, `0..24`. Equal to `from_hour` to disable dimming.
+    pub to_hour: u8,
+}
+
+impl Default for NightWindow {
+    fn default() -> Self {
+        Self {
+            from_hour: 22,
+            to_hour: 7,
+        }
+    }
+}
+
+impl NightWindow {
+    pub fn contains(&self, hour: u8) -> bool {
+        if self.from_hour <= self.to_hour {
+            self.from_hour <= hour && hour < self.to_hour
+        } else {
+            self.from_hour <= hour || hour < self.to_hour
+        }
+    }
+}
+
+/// The display duty cycle for the current time and battery level.
+pub fn duty_cycle(state: &State) -> f32 {
+    let mut duty_cycle = if state.settings.night_window.contains(state.rtc.hour) {
+        NIGHT_DUTY_CYCLE
+    } else {
+        DAY_DUTY_CYCLE
+    };
+
+    if let BatteryState::BelowNominal | BatteryState::Critical = state.bat_level {
+        duty_cycle *= LOW_BATTERY_FACTOR;
+    }
+
+    duty_cycle
+}
+
+const DAY_DUTY_CYCLE: f32 = 1.0;
+const NIGHT_DUTY_CYCLE: f32 = 0.2;
+// Stretches the run-time on battery.
+const LOW_BATTERY_FACTOR: f32 = 0.5;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn night_window_test() {
+        let night = NightWindow::default();
+        assert!(night.contains(22));
+        assert!(night.contains(0));
+        assert!(night.contains(6));
+        assert!(!night.contains(7));
+        assert!(!night.contains(21));
+
+        let night = NightWindow {
+            from_hour: 1,
+            to_hour: 5,
+        };
+        assert!(!night.contains(0));
+        assert!(night.contains(1));
+        assert!(!night.contains(5));
+
+        let night = NightWindow {
+            from_hour: 3,
+            to_hour: 3,
+        };
+        assert!((0..24).all(|hour| !night.contains(hour)));
+    }
+
+    #[test]
+    fn duty_cycle_test() {
+        let mut state = State::default();
+
+        state.rtc.hour = 12;
+        assert_eq!(duty_cycle(&state), DAY_DUTY_CYCLE);
+
+        state.rtc.hour = 23;
+        assert_eq!(duty_cycle(&state), NIGHT_DUTY_CYCLE);
+
+        state.bat_level = BatteryState::BelowNominal;
+        assert_eq!(duty_cycle(&state), NIGHT_DUTY_CYCLE * LOW_BATTERY_FACTOR);
+
+        state.bat_level = BatteryState::Charging;
+        assert_eq!(duty_cycle(&state), NIGHT_DUTY_CYCLE);
+    }
+}
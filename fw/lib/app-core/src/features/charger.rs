@@ -1,8 +1,9 @@
 use crate::{
     action::Action,
-    common::Duration,
+    common::{Duration, Instant},
+    event::Event,
     state::State,
-    task::{NextRun, Task},
+    task::{NextRun, Task, Topics},
 };
 
 pub enum ChargerAction {
@@ -24,16 +25,59 @@ pub enum BatteryState {
     Charged,
 }
 
-#[derive(Default)]
+/// The charge timers and the temperature limits, on top of the voltage criteria.
+///
+/// The temperature limits only apply while the temperature is measured, see
+/// [`State::bat_temperature`].
+#[derive(Copy, Clone, Debug)]
+pub struct ChargerConfig {
+    /// Stop charging after this long, whatever the voltage.
+    pub max_charge_time: Duration,
+    /// Stop charging this long after the voltage of either cell reaches [`NIMH_HIGH`].
+    pub top_off_time: Duration,
+    /// Ignore the −ΔV for this long after the charging starts, while the voltage settles.
+    pub min_charge_time: Duration,
+    /// Stop charging when the temperature rises faster than this, in °C per minute.
+    pub max_temperature_rise: f32,
+    /// The period the temperature rise is measured over, long enough to average out
+    /// the sensor noise.
+    pub temperature_rise_period: Duration,
+    /// Stop charging above this temperature, and do not start again until cooled down
+    /// by [`TEMPERATURE_HYSTERESIS_C`].
+    pub max_temperature_c: f32,
+}
+
+impl Default for ChargerConfig {
+    fn default() -> Self {
+        Self {
+            max_charge_time: Duration::from_ticks(16 * 3_600_000_000),
+            top_off_time: Duration::from_ticks(2 * 3_600_000_000),
+            min_charge_time: Duration::from_ticks(10 * 60_000_000),
+            max_temperature_rise: 0.1,
+            temperature_rise_period: Duration::from_ticks(10 * 60_000_000),
+            max_temperature_c: 45.0,
+        }
+    }
+}
+
 pub struct Charger {
+    config: ChargerConfig,
     bat_voltage: (f32, f32),
     state: ChargerState,
+    charge_started_at: Instant,
+    /// When the voltage of either cell reached [`NIMH_HIGH`] while charging.
+    high_since: Option<Instant>,
+    /// The temperature at the start of the current rise period.
+    temperature_since: Option<(Instant, f32)>,
 }
 
 enum ChargerState {
     Hold,
     Charge,
     Charged,
+    /// Charged by the timers, not charging again until the external power is cycled.
+    /// The batteries only discharge off the external power, so no discharge is missed.
+    TimedOut,
 }
 
 impl Default for ChargerState {
@@ -42,18 +86,41 @@ impl Default for ChargerState {
     }
 }
 
+impl Default for Charger {
+    fn default() -> Self {
+        Self::new(ChargerConfig::default())
+    }
+}
+
 impl Task<State, Action> for Charger {
     fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
         match self.state {
             ChargerState::Hold => self.run_hold(state),
             ChargerState::Charge => self.run_charge(state),
             ChargerState::Charged => self.run_charged(state),
+            ChargerState::TimedOut => self.run_timed_out(state),
         }
     }
+
+    /// Start or stop charging as soon as the external power changes.
+    fn subscriptions(&self) -> Topics {
+        Event::EXT_POWER
+    }
 }
 
 impl Charger {
-    fn enter(&mut self, state: ChargerState) -> Option<ChargerAction> {
+    pub fn new(config: ChargerConfig) -> Self {
+        Self {
+            config,
+            bat_voltage: (0.0, 0.0),
+            state: ChargerState::default(),
+            charge_started_at: Instant::from_ticks(0),
+            high_since: None,
+            temperature_since: None,
+        }
+    }
+
+    fn enter(&mut self, state: ChargerState, now: Instant) -> Option<ChargerAction> {
         match state {
             ChargerState::Hold => {
                 self.state = ChargerState::Hold;
@@ -61,12 +128,19 @@ impl Charger {
             }
             ChargerState::Charge => {
                 self.state = ChargerState::Charge;
+                self.charge_started_at = now;
+                self.high_since = None;
+                self.temperature_since = None;
                 Some(ChargerAction::Charge)
             }
             ChargerState::Charged => {
                 self.state = ChargerState::Charged;
                 Some(ChargerAction::Hold)
             }
+            ChargerState::TimedOut => {
+                self.state = ChargerState::TimedOut;
+                Some(ChargerAction::Hold)
+            }
         }
     }
 
@@ -76,8 +150,12 @@ impl Charger {
         state.bat_level = Self::bat_level(state.bat_voltage);
 
         let (v1, v2) = state.bat_voltage;
-        let action = if state.ext_power && v1 < NIMH_HIGH && v2 < NIMH_HIGH {
-            self.enter(ChargerState::Charge)
+        let action = if state.ext_power
+            && v1 < NIMH_HIGH
+            && v2 < NIMH_HIGH
+            && self.is_cool(state.bat_temperature)
+        {
+            self.enter(ChargerState::Charge, state.now)
         } else {
             None
         };
@@ -95,12 +173,25 @@ impl Charger {
         state.bat_level = BatteryState::Charging;
 
         let action = if !state.ext_power {
-            self.enter(ChargerState::Hold)
+            self.enter(ChargerState::Hold, state.now)
         } else {
             let (v1, v2) = state.bat_voltage;
+            if self.high_since.is_none() && (v1 >= NIMH_HIGH || v2 >= NIMH_HIGH) {
+                self.high_since = Some(state.now);
+            }
+
+            let is_ndv = (d1 <= NIMH_NDV || d2 <= NIMH_NDV)
+                && since(self.charge_started_at, state.now) >= self.config.min_charge_time;
             let is_adc_saturated = v1 + v2 >= 2.99;
-            if d1 <= NIMH_NDV || d2 <= NIMH_NDV || is_adc_saturated {
-                self.enter(ChargerState::Charged)
+            let is_heating = self.is_heating(state.now, state.bat_temperature);
+            if self.is_timed_out(state.now) {
+                self.enter(ChargerState::TimedOut, state.now)
+            } else if is_ndv
+                || is_adc_saturated
+                || is_heating
+                || self.is_overheated(state.bat_temperature)
+            {
+                self.enter(ChargerState::Charged, state.now)
             } else {
                 None
             }
@@ -118,11 +209,11 @@ impl Charger {
         state.bat_level = BatteryState::Charged;
 
         let action = if !state.ext_power {
-            self.enter(ChargerState::Hold)
+            self.enter(ChargerState::Hold, state.now)
         } else {
             let (v1, v2) = state.bat_voltage;
-            if v1 < NIMH_HIGH || v2 < NIMH_HIGH {
-                self.enter(ChargerState::Charge)
+            if (v1 < NIMH_HIGH || v2 < NIMH_HIGH) && self.is_cool(state.bat_temperature) {
+                self.enter(ChargerState::Charge, state.now)
             } else {
                 None
             }
@@ -134,6 +225,63 @@ impl Charger {
         )
     }
 
+    fn run_timed_out(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        self.bat_voltage = state.bat_voltage;
+
+        state.bat_level = BatteryState::Charged;
+
+        let action = if !state.ext_power {
+            self.enter(ChargerState::Hold, state.now)
+        } else {
+            None
+        };
+
+        (
+            action.map(Action::Battery),
+            NextRun::After(Duration::from_ticks(5_000_000)),
+        )
+    }
+
+    fn is_timed_out(&self, now: Instant) -> bool {
+        since(self.charge_started_at, now) >= self.config.max_charge_time
+            || self
+                .high_since
+                .is_some_and(|high_since| since(high_since, now) >= self.config.top_off_time)
+    }
+
+    /// Whether the temperature rose too fast over the last complete rise period.
+    fn is_heating(&mut self, now: Instant, temperature: Option<f32>) -> bool {
+        let Some(temperature) = temperature else {
+            return false;
+        };
+
+        match self.temperature_since {
+            Some((since_at, since_temperature))
+                if since(since_at, now) >= self.config.temperature_rise_period =>
+            {
+                self.temperature_since = Some((now, temperature));
+                let minutes = since(since_at, now).ticks() as f32 / 60_000_000.0;
+                (temperature - since_temperature) / minutes >= self.config.max_temperature_rise
+            }
+            Some(_) => false,
+            None => {
+                self.temperature_since = Some((now, temperature));
+                false
+            }
+        }
+    }
+
+    fn is_overheated(&self, temperature: Option<f32>) -> bool {
+        temperature.is_some_and(|temperature| temperature >= self.config.max_temperature_c)
+    }
+
+    /// Whether cool enough to start charging.
+    fn is_cool(&self, temperature: Option<f32>) -> bool {
+        temperature.is_none_or(|temperature| {
+            temperature < self.config.max_temperature_c - TEMPERATURE_HYSTERESIS_C
+        })
+    }
+
     fn bat_level(bat_voltage: (f32, f32)) -> BatteryState {
         match bat_voltage {
             (v1, v2) if v1 >= NIMH_MPV && v2 >= NIMH_MPV => BatteryState::AboveNominal,
@@ -143,6 +291,11 @@ impl Charger {
     }
 }
 
+fn since(instant: Instant, now: Instant) -> Duration {
+    now.checked_duration_since(instant)
+        .unwrap_or(Duration::from_ticks(0))
+}
+
 fn max2f(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
     (
         if a.0 >= b.0 { a.0 } else { b.0 },
@@ -162,3 +315,375 @@ pub const NIMH_NDV: f32 = -0.002;
 pub const NIMH_MPV: f32 = 1.25;
 // NiMH battery end of discharge voltage.
 const NIMH_EODV: f32 = 0.9;
+// Do not restart charging after an over-temperature cutoff until cooled down by this much.
+pub const TEMPERATURE_HYSTERESIS_C: f32 = 5.0;
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        battery_model::{Battery, Cell, CellParams},
+        task::{
+            scheduler::{Deadline, StaticScheduler},
+            InlineFnTask,
+        },
+    };
+
+    use super::*;
+
+    const HOUR: Duration = Duration::from_ticks(3_600_000_000);
+    const CHARGE_CURRENT_A: f32 = 0.2;
+    const LOAD_CURRENT_A: f32 = 0.012;
+
+    /// Runs the charger against the battery model, switching the charge current on its actions.
+    struct Bench {
+        charger: Charger,
+        battery: Battery,
+        state: State,
+        is_charging: bool,
+        has_thermometer: bool,
+    }
+
+    impl Bench {
+        fn new(params: [CellParams; 2], soc: f32) -> Self {
+            Self::with_config(ChargerConfig::default(), params, soc)
+        }
+
+        fn with_config(config: ChargerConfig, params: [CellParams; 2], soc: f32) -> Self {
+            Self {
+                charger: Charger::new(config),
+                battery: Battery::new(params.map(|params| Cell::new(params, soc))),
+                state: State {
+                    ext_power: true,
+                    ..Default::default()
+                },
+                is_charging: false,
+                has_thermometer: true,
+            }
+        }
+
+        fn current_a(&self) -> f32 {
+            match (self.state.ext_power, self.is_charging) {
+                (true, true) => CHARGE_CURRENT_A,
+                (true, false) => 0.0,
+                (false, _) => -LOAD_CURRENT_A,
+            }
+        }
+
+        fn run(&mut self) -> Duration {
+            self.state.bat_voltage = self.battery.bat_voltage(self.current_a());
+            self.state.bat_temperature = self.has_thermometer.then(|| {
+                let [cell1, cell2] = &self.battery.cells;
+                cell1.temperature_c().max(cell2.temperature_c())
+            });
+            let (action, next_run) = self.charger.run(&mut self.state);
+            if let Some(Action::Battery(action)) = action {
+                self.is_charging = matches!(action, ChargerAction::Charge);
+            }
+
+            let NextRun::After(delay) = next_run else {
+                panic!("the charger runs periodically");
+            };
+            self.battery.step(self.current_a(), delay);
+            self.state.now += delay;
+            delay
+        }
+
+        /// Run until the battery state is the given one, return how long it took.
+        fn run_until(&mut self, bat_level: BatteryState, timeout: Duration) -> Option<Duration> {
+            let mut elapsed = Duration::from_ticks(0);
+            while self.state.bat_level != bat_level {
+                if elapsed >= timeout {
+                    return None;
+                }
+                elapsed += self.run();
+            }
+            Some(elapsed)
+        }
+
+        /// Run for the duration, return whether the battery state stayed the same.
+        fn run_steady(&mut self, duration: Duration) -> bool {
+            let bat_level = self.state.bat_level;
+            let mut elapsed = Duration::from_ticks(0);
+            while elapsed < duration {
+                elapsed += self.run();
+                if self.state.bat_level != bat_level {
+                    return false;
+                }
+            }
+            true
+        }
+    }
+
+    #[test]
+    fn hold_test() {
+        // Not charging without the external power.
+        let mut bench = Bench::new(Default::default(), 0.5);
+        bench.state.set_ext_power(false);
+        assert!(bench.run_steady(HOUR));
+        assert_eq!(bench.state.bat_level, BatteryState::AboveNominal);
+        assert!(!bench.is_charging);
+
+        // Nor when charged already.
+        let mut bench = Bench::new(Default::default(), 1.0);
+        assert!(bench.run_steady(HOUR));
+        assert!(!bench.is_charging);
+    }
+
+    #[test]
+    fn hold_to_charge_test() {
+        let mut bench = Bench::new(Default::default(), 0.5);
+        bench.run();
+        assert!(bench.is_charging);
+        assert!(bench.run_until(BatteryState::Charging, HOUR).is_some());
+    }
+
+    #[test]
+    fn charge_to_charged_ndv_test() {
+        let mut bench = Bench::new(Default::default(), 0.5);
+        bench.has_thermometer = false;
+
+        let charge_time = bench.run_until(BatteryState::Charged, 10 * HOUR).unwrap();
+        assert!(charge_time > 4 * HOUR && charge_time < 6 * HOUR);
+        assert!(!bench.is_charging);
+        for cell in &bench.battery.cells {
+            assert!(cell.soc() > 0.9);
+            assert!(cell.temperature_c() > cell.params.ambient_c + 1.0);
+        }
+        let (v1, v2) = bench.state.bat_voltage;
+        assert!(v1 + v2 < 2.99);
+
+        // Topped off, not charged again while resting.
+        assert!(bench.run_steady(10 * HOUR));
+    }
+
+    #[test]
+    fn charge_to_charged_saturated_test() {
+        // The charging voltage of the worn second cell saturates the ADC.
+        let worn = CellParams {
+            internal_resistance_ohm: 2.0,
+            ..Default::default()
+        };
+        let mut bench = Bench::new([Default::default(), worn], 0.8);
+
+        bench.run();
+        assert!(bench.is_charging);
+        let charge_time = bench.run_until(BatteryState::Charged, HOUR).unwrap();
+        assert!(charge_time <= Duration::from_ticks(10_000_000));
+        assert!(bench.battery.cells[0].soc() < 0.81);
+
+        // Back to charging right away, the resting voltage being low.
+        assert!(bench.is_charging);
+    }
+
+    #[test]
+    fn charged_to_charge_test() {
+        // Self-discharging fast enough to need a top-off within days.
+        let leaky = CellParams {
+            self_discharge_per_day: 0.1,
+            ..Default::default()
+        };
+        let mut bench = Bench::new([leaky; 2], 0.5);
+        bench.has_thermometer = false;
+        bench.run_until(BatteryState::Charged, 10 * HOUR).unwrap();
+
+        let rest_time = bench.run_until(BatteryState::Charging, 72 * HOUR).unwrap();
+        assert!(rest_time > 12 * HOUR);
+        assert!(bench.is_charging);
+
+        // Back to charged.
+        assert!(bench.run_until(BatteryState::Charged, 10 * HOUR).is_some());
+    }
+
+    #[test]
+    fn charge_to_hold_test() {
+        let mut bench = Bench::new(Default::default(), 0.5);
+        bench.run_until(BatteryState::Charging, HOUR).unwrap();
+
+        bench.state.set_ext_power(false);
+        bench.run();
+        assert!(!bench.is_charging);
+        assert!(bench.run_until(BatteryState::AboveNominal, HOUR).is_some());
+        assert!(bench.run_steady(HOUR));
+    }
+
+    #[test]
+    fn charged_to_hold_test() {
+        let mut bench = Bench::new(Default::default(), 0.5);
+        bench.run_until(BatteryState::Charged, 10 * HOUR).unwrap();
+
+        bench.state.set_ext_power(false);
+        bench.run();
+        assert!(!bench.is_charging);
+        assert!(bench.run_until(BatteryState::AboveNominal, HOUR).is_some());
+
+        // Discharged under the load, then charged again once plugged.
+        assert!(bench
+            .run_until(BatteryState::BelowNominal, 200 * HOUR)
+            .is_some());
+        bench.state.set_ext_power(true);
+        assert!(bench.run_until(BatteryState::Charging, HOUR).is_some());
+    }
+
+    #[test]
+    fn top_off_timeout_test() {
+        // No heating, no −ΔV.
+        let cool = CellParams {
+            temperature_coefficient_v_per_k: 0.0,
+            ..Default::default()
+        };
+        let mut bench = Bench::new([cool; 2], 0.5);
+        bench.has_thermometer = false;
+
+        bench.run_until(BatteryState::Charging, HOUR).unwrap();
+        let (v1, _) = bench.state.bat_voltage;
+        assert!(v1 < NIMH_HIGH);
+        let mut high_time = Duration::from_ticks(0);
+        while bench.state.bat_voltage.0 < NIMH_HIGH {
+            high_time += bench.run();
+        }
+
+        let top_off_time = bench.run_until(BatteryState::Charged, 10 * HOUR).unwrap();
+        let expected = ChargerConfig::default().top_off_time;
+        assert!(top_off_time >= expected);
+        assert!(top_off_time <= expected + Duration::from_ticks(10_000_000));
+        assert!(high_time > 3 * HOUR);
+    }
+
+    #[test]
+    fn max_charge_time_test() {
+        let config = ChargerConfig {
+            max_charge_time: HOUR,
+            ..Default::default()
+        };
+        let mut bench = Bench::with_config(config, Default::default(), 0.2);
+
+        let charge_time = bench.run_until(BatteryState::Charged, 10 * HOUR).unwrap();
+        assert!(charge_time >= HOUR);
+        assert!(charge_time <= HOUR + Duration::from_ticks(10_000_000));
+        let (v1, v2) = bench.state.bat_voltage;
+        assert!(v1 < NIMH_HIGH && v2 < NIMH_HIGH);
+
+        // Not charging again below the voltage criteria, until the external power is cycled.
+        assert!(bench.run_steady(10 * HOUR));
+        assert!(!bench.is_charging);
+
+        bench.state.set_ext_power(false);
+        bench.run();
+        bench.state.set_ext_power(true);
+        assert!(bench
+            .run_until(BatteryState::Charging, Duration::from_ticks(10_000_000))
+            .is_some());
+    }
+
+    #[test]
+    fn min_charge_time_test() {
+        let mut charger = Charger::default();
+        let mut state = State {
+            ext_power: true,
+            ..Default::default()
+        };
+        // Whether the charging stops.
+        let mut run = |at_s: u64, v: f32| {
+            state.now = Instant::from_ticks(at_s * 1_000_000);
+            state.bat_voltage = (v, v);
+            let (action, _) = charger.run(&mut state);
+            matches!(action, Some(Action::Battery(ChargerAction::Hold)))
+        };
+
+        // Charging from here on.
+        assert!(!run(0, 1.30));
+        assert!(!run(5, 1.32));
+        // The voltage settling down right after the charging starts.
+        assert!(!run(10, 1.31));
+        assert!(!run(595, 1.30));
+        assert!(!run(600, 1.33));
+        assert!(run(605, 1.32));
+    }
+
+    #[test]
+    fn temperature_rise_test() {
+        let mut bench = Bench::new(Default::default(), 0.5);
+        let charge_time = bench.run_until(BatteryState::Charged, 10 * HOUR).unwrap();
+
+        // Ahead of the −ΔV.
+        let mut ndv_bench = Bench::new(Default::default(), 0.5);
+        ndv_bench.has_thermometer = false;
+        let ndv_charge_time = ndv_bench
+            .run_until(BatteryState::Charged, 10 * HOUR)
+            .unwrap();
+        assert!(charge_time + Duration::from_ticks(10 * 60_000_000) < ndv_charge_time);
+        for cell in &bench.battery.cells {
+            assert!(cell.soc() > 0.9);
+        }
+    }
+
+    #[test]
+    fn over_temperature_test() {
+        let config = ChargerConfig {
+            max_temperature_rise: f32::INFINITY,
+            ..Default::default()
+        };
+        // Poorly cooled, heating up well ahead of the full charge, with no −ΔV.
+        let warm = CellParams {
+            ambient_c: 38.0,
+            internal_resistance_ohm: 0.5,
+            thermal_resistance_k_per_w: 400.0,
+            temperature_coefficient_v_per_k: 0.0,
+            ..Default::default()
+        };
+        let mut bench = Bench::with_config(config, [warm; 2], 0.5);
+
+        bench.run_until(BatteryState::Charged, 10 * HOUR).unwrap();
+        // Cooling down a little since the cutoff.
+        let temperature = bench.state.bat_temperature.unwrap();
+        assert!((temperature - config.max_temperature_c).abs() < 0.1);
+
+        assert!(bench.battery.cells[0].soc() < 0.6);
+
+        // Not charging again until cooled down.
+        let rest_time = bench.run_until(BatteryState::Charging, HOUR).unwrap();
+        assert!(rest_time > Duration::from_ticks(10 * 60_000_000));
+        assert!(
+            bench.state.bat_temperature.unwrap()
+                < config.max_temperature_c - TEMPERATURE_HYSTERESIS_C + 0.1
+        );
+
+        // Nor starting to charge when too hot.
+        let hot = CellParams {
+            ambient_c: 46.0,
+            ..Default::default()
+        };
+        let mut bench = Bench::with_config(config, [hot; 2], 0.5);
+        assert!(bench.run_steady(HOUR));
+        assert!(!bench.is_charging);
+    }
+
+    #[test]
+    fn wrapped_wake_test() {
+        // The way the app wraps the charger, to sample the ADC right before every run.
+        let mut charger = Charger::default();
+        let subscriptions = charger.subscriptions();
+        let mut task = InlineFnTask::new(move |state: &mut State| charger.run(state))
+            .with_subscriptions(subscriptions);
+        let mut scheduler = StaticScheduler::new([&mut task as _]);
+        let mut state = State::default();
+        let mut is_charging = false;
+
+        scheduler.run(Instant::from_ticks(0), &mut state, &mut |_| {});
+        assert_eq!(
+            scheduler.next_deadline(),
+            Deadline::At(Instant::from_ticks(5_000_000))
+        );
+
+        state.set_ext_power(true);
+        let now = Instant::from_ticks(100_000);
+        scheduler.wake(now, state.events.take_published());
+        assert_eq!(scheduler.next_deadline(), Deadline::At(now));
+
+        state.now = now;
+        scheduler.run(now, &mut state, &mut |action| {
+            is_charging |= matches!(action, Action::Battery(ChargerAction::Charge));
+        });
+        assert!(is_charging);
+    }
+}
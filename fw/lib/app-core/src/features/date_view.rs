@@ -0,0 +1,125 @@
+use crate::{
+    action::Action,
+    common::Duration,
+    event::{Event, EventsCursor},
+    state::State,
+    task::{NextRun, Task, Topics},
+};
+
+use super::{
+    display::View,
+    stopwatch::Stopwatch,
+    touch::{Button, ButtonEvent},
+};
+
+/// Switches the display between the time and the date.
+///
+/// A press on [`Button::B`] shows the date for [`DATE_VIEW_MS`]. With
+/// `settings.show_date_periodically`, the date is also shown once a minute,
+/// starting at [`DATE_VIEW_SECOND`].
+#[derive(Default)]
+pub struct DateView {
+    events: EventsCursor,
+    /// The time since the date was asked for.
+    shown: Stopwatch,
+    last_second: u8,
+}
+
+impl Task<State, Action> for DateView {
+    fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        while let Some(event) = state.events.read(&mut self.events) {
+            let Event::Button(event) = event else {
+                continue;
+            };
+            if event == ButtonEvent::Press(Button::B)
+                && state.set_time.is_none()
+                && state.set_alarm.is_none()
+                && !state.alarm.is_ringing()
+            {
+                self.shown.start(state.now);
+            }
+        }
+
+        let second = state.rtc.second;
+        if state.settings.show_date_periodically
+            && second == DATE_VIEW_SECOND
+            && self.last_second != second
+        {
+            self.shown.start(state.now);
+        }
+        self.last_second = second;
+
+        state.view = if self
+            .shown
+            .is_within(state.now, Duration::from_ticks(DATE_VIEW_MS * 1_000))
+        {
+            View::Date
+        } else {
+            View::Time
+        };
+
+        (
+            None,
+            NextRun::After(Duration::from_ticks(DATE_VIEW_PERIOD_US)),
+        )
+    }
+
+    fn subscriptions(&self) -> Topics {
+        Event::BUTTON
+    }
+}
+
+const DATE_VIEW_PERIOD_US: u64 = 100_000;
+pub const DATE_VIEW_MS: u64 = 3_000;
+pub const DATE_VIEW_SECOND: u8 = 30;
+
+#[cfg(test)]
+mod tests {
+    use crate::features::stopwatch::run_for;
+
+    use super::*;
+
+    #[test]
+    fn date_view_test() {
+        let mut date_view = DateView::default();
+        let mut state = State::default();
+        state.settings.show_date_periodically = false;
+
+        date_view.run(&mut state);
+        assert_eq!(state.view, View::Time);
+
+        state
+            .events
+            .publish(Event::Button(ButtonEvent::Press(Button::B)));
+        // Woken up by the events of other features, without the time moving on.
+        for _ in 0..100 {
+            date_view.run(&mut state);
+            assert_eq!(state.view, View::Date);
+        }
+        run_for(
+            &mut date_view,
+            &mut state,
+            DATE_VIEW_PERIOD_US,
+            DATE_VIEW_MS - DATE_VIEW_PERIOD_US / 1_000,
+        );
+        assert_eq!(state.view, View::Date);
+        run_for(
+            &mut date_view,
+            &mut state,
+            DATE_VIEW_PERIOD_US,
+            DATE_VIEW_PERIOD_US / 1_000,
+        );
+        assert_eq!(state.view, View::Time);
+
+        state.rtc.second = DATE_VIEW_SECOND;
+        date_view.run(&mut state);
+        assert_eq!(state.view, View::Time);
+
+        state.settings.show_date_periodically = true;
+        state.rtc.second = DATE_VIEW_SECOND - 1;
+        date_view.run(&mut state);
+        state.rtc.second = DATE_VIEW_SECOND;
+        date_view.run(&mut state);
+        assert_eq!(state.view, View::Date);
+    }
+}
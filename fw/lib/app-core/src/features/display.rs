@@ -1,51 +1,449 @@
 use crate::{
     action::Action,
     common::Duration,
+    settings::Settings,
     state::State,
-    task::{NextRun, Task},
+    task::{NextRun, Priority, Task},
 };
-use seg_disp::{char7dp::Char7DP, char7dp_seq::Char7DPSeq};
+use seg_disp::{
+    char7dp::Char7DP,
+    char7dp_seq::Char7DPSeq,
+    disp::{Frame, ScanMode},
+};
+
+use super::{
+    alarm::AlarmState,
+    brightness,
+    charger::BatteryState,
+    power::PowerState,
+    set_alarm::{AlarmDays, AlarmField, SetAlarmState},
+    set_time::{SetTimeState, TimeField},
+};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum View {
+    Time,
+    Date,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum DateFormat {
+    /// DD.MM
+    DayMonth,
+    /// MM.DD
+    MonthDay,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum HourMode {
+    H24,
+    /// 12-hour mode, PM is indicated by the decimal point of the rightmost digit.
+    H12,
+}
+
+impl HourMode {
+    /// Return the hour to display and whether it's PM.
+    pub fn hour(self, hour: u8) -> (u8, bool) {
+        match self {
+            HourMode::H24 => (hour, false),
+            HourMode::H12 => (
+                match hour % 12 {
+                    0 => 12,
+                    hour => hour,
+                },
+                hour >= 12,
+            ),
+        }
+    }
+}
 
-use super::charger::BatteryState;
+pub const DIGIT_COUNT: usize = 4;
+
+/// The way the display is refreshed.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum Refresh {
+    /// Every multiplexing step is a separate run, reported by [`Action::Display`].
+    #[default]
+    Scheduled,
+    /// The driver refreshes the display on its own, a new frame is reported
+    /// by [`Action::DisplayFrame`] only when the content changes.
+    Streamed,
+}
 
 pub struct Display {
-    disp: seg_disp::disp::Disp<4>,
+    disp: seg_disp::disp::Disp<DIGIT_COUNT>,
+    refresh: Refresh,
+    /// The last frame reported in [`Refresh::Streamed`].
+    frame: Option<Frame<DIGIT_COUNT>>,
 }
 
 impl Default for Display {
     fn default() -> Self {
+        Self::new(Refresh::default(), ScanMode::default())
+    }
+}
+
+impl Display {
+    /// The segment compensation and the digit gain only apply in [`ScanMode::Digit`].
+    pub fn new(refresh: Refresh, scan_mode: ScanMode) -> Self {
+        let mut disp = seg_disp::disp::Disp::new(Duration::from_ticks(2_000), 1.0);
+        disp.set_scan_mode(scan_mode);
+        disp.set_segment_compensation(SEGMENT_COMPENSATION);
+        disp.set_digit_gain(DIGIT_GAIN);
+
         Self {
-            disp: seg_disp::disp::Disp::new(Duration::from_ticks(2_000), 1.0),
+            disp,
+            refresh,
+            frame: None,
         }
     }
 }
 
+// Partial compensation, 1.0 would equalize the digits for a purely shared common current.
+const SEGMENT_COMPENSATION: f32 = 0.5;
+// Per-indicator calibration, from the rightmost digit to the leftmost one.
+const DIGIT_GAIN: [f32; DIGIT_COUNT] = [1.0, 1.0, 1.0, 1.0];
+// How often the content is checked for changes in `Refresh::Streamed`.
+const FRAME_CHECK_PERIOD_US: u64 = 20_000;
+
 impl Task<State, Action> for Display {
     fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
-        let mut time = [Char7DP::space(); 4];
+        let mut time = [Char7DP::space(); DIGIT_COUNT];
+
+        if let AlarmState::Ringing {
+            is_visible: false, ..
+        } = state.alarm
+        {
+            // Flashing.
+        } else if state.power == PowerState::Standby {
+            // Blank.
+        } else if let Some(set_time) = state.set_time {
+            Self::render_set_time(&mut time, set_time, &state.settings);
+        } else if let Some(set_alarm) = state.set_alarm {
+            Self::render_set_alarm(&mut time, set_alarm, state.settings.hour_mode);
+        } else if state.view == View::Date && state.bat_level != BatteryState::Critical {
+            Self::render_date(&mut time, state);
+        } else {
+            Self::render_time(&mut time, state);
+        }
+
+        self.disp.set_chars(time);
+        self.disp.set_duty_cycle(brightness::duty_cycle(state));
+
+        match self.refresh {
+            Refresh::Scheduled => {
+                let (action, delay) = self.disp.run();
+
+                (Some(Action::Display(action)), NextRun::After(delay))
+            }
+            Refresh::Streamed => {
+                let frame = self.disp.frame();
+                let action = (self.frame != Some(frame)).then(|| {
+                    self.frame = Some(frame);
+                    Action::DisplayFrame(frame)
+                });
+
+                (
+                    action,
+                    NextRun::After(Duration::from_ticks(FRAME_CHECK_PERIOD_US)),
+                )
+            }
+        }
+    }
+
+    fn priority(&self) -> Priority {
+        // A late multiplexing step is seen as flicker.
+        Priority::High
+    }
+}
+
+impl Display {
+    fn render_time(time: &mut [Char7DP; DIGIT_COUNT], state: &State) {
+        // The rightmost decimal point is the PM indicator in the 12-hour mode, keep it
+        // out of the blinking and the animation.
+        let first_free_dp = match state.settings.hour_mode {
+            HourMode::H24 => 0,
+            HourMode::H12 => 1,
+        };
 
         match state.bat_level {
             BatteryState::Critical => {
-                time[0].set_dp(state.rtc.second & 1 == 0);
+                time[first_free_dp].set_dp(state.rtc.second & 1 == 0);
             }
             BatteryState::BelowNominal | BatteryState::AboveNominal | BatteryState::Charged => {
-                let (hour, minute, second) = (state.rtc.hour, state.rtc.minute, state.rtc.second);
+                let (minute, second) = (state.rtc.minute, state.rtc.second);
+                let (hour, is_pm) = state.settings.hour_mode.hour(state.rtc.hour);
                 Char7DPSeq::new(&mut time[0..2]).set_dec(minute as usize, true);
                 Char7DPSeq::new(&mut time[2..4]).set_dec(hour as usize, false);
                 time[2].set_dp(second & 1 == 0);
+                time[0].set_dp(is_pm);
             }
             BatteryState::Charging => {
-                let (hour, minute, second) = (state.rtc.hour, state.rtc.minute, state.rtc.second);
+                let (minute, second) = (state.rtc.minute, state.rtc.second);
+                let (hour, is_pm) = state.settings.hour_mode.hour(state.rtc.hour);
                 Char7DPSeq::new(&mut time[0..2]).set_dec(minute as usize, true);
                 Char7DPSeq::new(&mut time[2..4]).set_dec(hour as usize, false);
-                time[second as usize % 4].set_dp(true);
+                let step_count = DIGIT_COUNT - first_free_dp;
+                time[first_free_dp + second as usize % step_count].set_dp(true);
+                if is_pm {
+                    time[0].set_dp(true);
+                }
             }
         }
+    }
 
-        self.disp.set_chars(time);
+    fn render_date(time: &mut [Char7DP; DIGIT_COUNT], state: &State) {
+        let (first, second) = match state.settings.date_format {
+            DateFormat::DayMonth => (state.rtc.day, state.rtc.month),
+            DateFormat::MonthDay => (state.rtc.month, state.rtc.day),
+        };
+        Char7DPSeq::new(&mut time[0..2]).set_dec(second as usize, true);
+        Char7DPSeq::new(&mut time[2..4]).set_dec(first as usize, false);
+        time[2].set_dp(true);
+    }
+
+    fn render_set_time(
+        time: &mut [Char7DP; DIGIT_COUNT],
+        set_time: SetTimeState,
+        settings: &Settings,
+    ) {
+        let is_visible = |field| set_time.field != field || set_time.is_field_visible;
+
+        match set_time.field {
+            TimeField::Year => {
+                if is_visible(TimeField::Year) {
+                    Char7DPSeq::new(&mut time[0..4]).set_dec(set_time.year as usize, true);
+                }
+            }
+            TimeField::Month | TimeField::Day => {
+                // As in the date view.
+                let (first, second) = match settings.date_format {
+                    DateFormat::DayMonth => (
+                        (set_time.day, TimeField::Day),
+                        (set_time.month, TimeField::Month),
+                    ),
+                    DateFormat::MonthDay => (
+                        (set_time.month, TimeField::Month),
+                        (set_time.day, TimeField::Day),
+                    ),
+                };
+                if is_visible(second.1) {
+                    Char7DPSeq::new(&mut time[0..2]).set_dec(second.0 as usize, true);
+                }
+                if is_visible(first.1) {
+                    Char7DPSeq::new(&mut time[2..4]).set_dec(first.0 as usize, false);
+                }
+                time[2].set_dp(true);
+            }
+            TimeField::Hour | TimeField::Minute => {
+                let (hour, is_pm) = settings.hour_mode.hour(set_time.hour);
+                if is_visible(TimeField::Minute) {
+                    Char7DPSeq::new(&mut time[0..2]).set_dec(set_time.minute as usize, true);
+                }
+                if is_visible(TimeField::Hour) {
+                    Char7DPSeq::new(&mut time[2..4]).set_dec(hour as usize, false);
+                }
+                time[2].set_dp(true);
+                time[0].set_dp(is_pm);
+            }
+        }
+    }
+
+    fn render_set_alarm(
+        time: &mut [Char7DP; DIGIT_COUNT],
+        set_alarm: SetAlarmState,
+        hour_mode: HourMode,
+    ) {
+        let is_visible = |field| set_alarm.field != field || set_alarm.is_field_visible;
+        let alarm = set_alarm.alarm;
+
+        match set_alarm.field {
+            AlarmField::Index => {
+                // "-- N", the alarm number.
+                time[3] = Char7DP::try_from_char('-').unwrap();
+                time[2] = Char7DP::try_from_char('-').unwrap();
+                if is_visible(AlarmField::Index) {
+                    Char7DPSeq::new(&mut time[0..1]).set_dec(set_alarm.index + 1, false);
+                }
+            }
+            AlarmField::Hour | AlarmField::Minute => {
+                let (hour, is_pm) = hour_mode.hour(alarm.hour);
+                if is_visible(AlarmField::Minute) {
+                    Char7DPSeq::new(&mut time[0..2]).set_dec(alarm.minute as usize, true);
+                }
+                if is_visible(AlarmField::Hour) {
+                    Char7DPSeq::new(&mut time[2..4]).set_dec(hour as usize, false);
+                }
+                time[2].set_dp(true);
+                time[0].set_dp(is_pm);
+            }
+            AlarmField::Days => {
+                // "----" when off, otherwise the day range, Monday being 1.
+                let days = match AlarmDays::of(&alarm) {
+                    AlarmDays::Off => "----",
+                    AlarmDays::MonFri => " 1-5",
+                    AlarmDays::All => " 1-7",
+                    AlarmDays::Weekend => " 6-7",
+                };
+                if is_visible(AlarmField::Days) {
+                    for (char, c) in time.iter_mut().rev().zip(days.chars()) {
+                        *char = Char7DP::try_from_char(c).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use seg_disp::char7dp::Segment7DP;
+
+    use super::*;
+
+    #[test]
+    fn set_time_test() {
+        let mut set_time = SetTimeState {
+            field: TimeField::Year,
+            year: 2023,
+            month: 4,
+            day: 7,
+            hour: 9,
+            minute: 1,
+            is_field_visible: true,
+        };
+        let mut settings = Settings::default();
+        let render = |set_time, settings: &Settings| {
+            let mut time = [Char7DP::space(); DIGIT_COUNT];
+            Display::render_set_time(&mut time, set_time, settings);
+            time
+        };
+        // From the leftmost digit, unlike the display, with the separator.
+        let chars = |s: &str, separator: bool| {
+            let mut chars = [Char7DP::space(); DIGIT_COUNT];
+            for (char, c) in chars.iter_mut().rev().zip(s.chars()) {
+                *char = Char7DP::try_from_char(c).unwrap();
+            }
+            chars[2].set_dp(separator);
+            chars
+        };
+
+        assert_eq!(render(set_time, &settings), chars("2023", false));
+        set_time.is_field_visible = false;
+        assert_eq!(render(set_time, &settings), [Char7DP::space(); DIGIT_COUNT]);
+
+        // The blinking month.
+        set_time.field = TimeField::Month;
+        settings.date_format = DateFormat::DayMonth;
+        assert_eq!(render(set_time, &settings), chars(" 7  ", true));
+        settings.date_format = DateFormat::MonthDay;
+        assert_eq!(render(set_time, &settings), chars("  07", true));
+
+        set_time.field = TimeField::Minute;
+        assert_eq!(render(set_time, &settings), chars(" 9  ", true));
+    }
+
+    #[test]
+    fn hour_mode_test() {
+        assert_eq!(HourMode::H24.hour(0), (0, false));
+        assert_eq!(HourMode::H24.hour(13), (13, false));
+        assert_eq!(HourMode::H12.hour(0), (12, false));
+        assert_eq!(HourMode::H12.hour(11), (11, false));
+        assert_eq!(HourMode::H12.hour(12), (12, true));
+        assert_eq!(HourMode::H12.hour(23), (11, true));
+    }
+
+    #[test]
+    fn pm_indicator_test() {
+        let render = |bat_level, hour, second| {
+            let mut state = State {
+                bat_level,
+                ..Default::default()
+            };
+            state.settings.hour_mode = HourMode::H12;
+            state.rtc.hour = hour;
+            state.rtc.second = second;
+            let mut time = [Char7DP::space(); DIGIT_COUNT];
+            Display::render_time(&mut time, &state);
+            time.map(|char| char.is_set(Segment7DP::DP))
+        };
+
+        // The charging animation skips the PM indicator.
+        for second in 0..60 {
+            assert!(!render(BatteryState::Charging, 9, second)[0]);
+            assert!(render(BatteryState::Charging, 21, second)[0]);
+            assert_eq!(
+                render(BatteryState::Charging, 9, second)[1..]
+                    .iter()
+                    .filter(|&&dp| dp)
+                    .count(),
+                1
+            );
+        }
+        assert!(!render(BatteryState::Critical, 9, 0)[0]);
+        assert!(render(BatteryState::Critical, 9, 0)[1]);
+    }
+
+    #[test]
+    fn set_alarm_test() {
+        let mut set_alarm = SetAlarmState {
+            field: AlarmField::Days,
+            index: 0,
+            alarm: Default::default(),
+            is_field_visible: true,
+        };
+        let render = |set_alarm| {
+            let mut time = [Char7DP::space(); DIGIT_COUNT];
+            Display::render_set_alarm(&mut time, set_alarm, HourMode::H24);
+            time
+        };
+
+        // From the leftmost digit, unlike the display.
+        let chars = |s: &str| {
+            let mut chars = [Char7DP::space(); DIGIT_COUNT];
+            for (char, c) in chars.iter_mut().rev().zip(s.chars()) {
+                *char = Char7DP::try_from_char(c).unwrap();
+            }
+            chars
+        };
+
+        assert_eq!(render(set_alarm), chars("----"));
+        set_alarm.alarm.enabled = true;
+        assert_eq!(render(set_alarm), chars(" 1-5"));
+
+        set_alarm.is_field_visible = false;
+        assert_eq!(render(set_alarm), [Char7DP::space(); DIGIT_COUNT]);
+    }
+
+    #[test]
+    fn streamed_refresh_test() {
+        let mut display = Display::new(Refresh::Streamed, ScanMode::default());
+        let mut state = State::default();
+
+        assert!(matches!(
+            display.run(&mut state),
+            (Some(Action::DisplayFrame(_)), _)
+        ));
+        assert!(matches!(display.run(&mut state), (None, _)));
+
+        state.rtc.minute += 1;
+        assert!(matches!(
+            display.run(&mut state),
+            (Some(Action::DisplayFrame(_)), _)
+        ));
+    }
 
-        let (action, delay) = self.disp.run();
+    #[test]
+    fn segment_scan_test() {
+        let mut display = Display::new(Refresh::Scheduled, ScanMode::Segment);
+        let mut state = State::default();
 
-        (Some(Action::Display(action)), NextRun::After(delay))
+        assert!(matches!(
+            display.run(&mut state),
+            (
+                Some(Action::Display(seg_disp::disp::Action::RenderSegment(..))),
+                _
+            )
+        ));
     }
 }
@@ -0,0 +1,159 @@
+use crate::{
+    action::Action,
+    common::Duration,
+    event::{Event, EventsCursor},
+    state::State,
+    task::{NextRun, Task, Topics},
+};
+
+use super::stopwatch::Stopwatch;
+
+pub enum PowerAction {
+    /// Enter a low-power state until the next wake-up, the display is already blank.
+    Sleep,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum PowerState {
+    On,
+    /// The display is blank and the app sleeps between the wake-ups.
+    Standby,
+}
+
+/// Saves the battery when nobody is around.
+///
+/// On battery, after [`POWER_SAVE_TIMEOUT_MS`] without events in `state.events` the display
+/// goes blank and the app is asked to sleep. After every wake-up, the other tasks get
+/// [`WAKE_WINDOW_MS`] to pick up a touch or to ring an alarm before the app is asked
+/// to sleep again. An event, the external power or a ringing alarm turn the display
+/// back on.
+#[derive(Default)]
+pub struct PowerSaver {
+    events: EventsCursor,
+    /// The time without activity, since the last wake-up in standby. Stopped while asleep.
+    idle: Stopwatch,
+}
+
+impl Task<State, Action> for PowerSaver {
+    fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        let mut is_active = state.ext_power
+            || state.alarm.is_ringing()
+            || state.set_time.is_some()
+            || state.set_alarm.is_some();
+        while state.events.read(&mut self.events).is_some() {
+            is_active = true;
+        }
+
+        let mut action = None;
+
+        if is_active {
+            self.idle.start(state.now);
+            state.power = PowerState::On;
+        } else {
+            if self.idle.elapsed(state.now).is_none() {
+                // The first run after a wake-up.
+                self.idle.start(state.now);
+            }
+            match state.power {
+                PowerState::On => {
+                    if self.idle.has_passed(
+                        state.now,
+                        Duration::from_ticks(POWER_SAVE_TIMEOUT_MS * 1_000),
+                    ) {
+                        // The display gets a wake window to go blank before the first sleep.
+                        self.idle.start(state.now);
+                        state.power = PowerState::Standby;
+                    }
+                }
+                PowerState::Standby => {
+                    if self
+                        .idle
+                        .has_passed(state.now, Duration::from_ticks(WAKE_WINDOW_MS * 1_000))
+                    {
+                        self.idle.stop();
+                        action = Some(Action::Power(PowerAction::Sleep));
+                    }
+                }
+            }
+        }
+
+        (
+            action,
+            NextRun::After(Duration::from_ticks(POWER_SAVER_PERIOD_US)),
+        )
+    }
+
+    fn subscriptions(&self) -> Topics {
+        Event::EXT_POWER
+    }
+}
+
+const POWER_SAVER_PERIOD_US: u64 = 20_000;
+pub const POWER_SAVE_TIMEOUT_MS: u64 = 60_000;
+// Enough for the touch pads to be debounced.
+pub const WAKE_WINDOW_MS: u64 = 60;
+
+#[cfg(test)]
+mod tests {
+    use crate::features::{
+        stopwatch::run_for,
+        touch::{Button, ButtonEvent},
+    };
+
+    use super::*;
+
+    /// The number of the sleeps asked for over `ms`.
+    fn sleeps_for(power_saver: &mut PowerSaver, state: &mut State, ms: u64) -> usize {
+        run_for(power_saver, state, POWER_SAVER_PERIOD_US, ms)
+            .iter()
+            .filter(|action| matches!(action, Action::Power(PowerAction::Sleep)))
+            .count()
+    }
+
+    #[test]
+    fn standby_test() {
+        let mut power_saver = PowerSaver::default();
+        let mut state = State::default();
+
+        // Woken up by the events of other features, without the time moving on.
+        for _ in 0..POWER_SAVE_TIMEOUT_MS {
+            assert!(power_saver.run(&mut state).0.is_none());
+        }
+        assert_eq!(state.power, PowerState::On);
+
+        assert_eq!(
+            sleeps_for(&mut power_saver, &mut state, POWER_SAVE_TIMEOUT_MS),
+            0
+        );
+        assert_eq!(state.power, PowerState::Standby);
+
+        // Asleep until the next second, then awake for the wake window.
+        assert_eq!(sleeps_for(&mut power_saver, &mut state, WAKE_WINDOW_MS), 1);
+        for _ in 0..3 {
+            state.now += Duration::from_ticks(1_000_000);
+            assert!(power_saver.run(&mut state).0.is_none());
+            assert_eq!(sleeps_for(&mut power_saver, &mut state, WAKE_WINDOW_MS), 1);
+        }
+
+        state
+            .events
+            .publish(Event::Button(ButtonEvent::Press(Button::A)));
+        assert_eq!(sleeps_for(&mut power_saver, &mut state, WAKE_WINDOW_MS), 0);
+        assert_eq!(state.power, PowerState::On);
+    }
+
+    #[test]
+    fn ext_power_test() {
+        let mut power_saver = PowerSaver::default();
+        let mut state = State {
+            ext_power: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            sleeps_for(&mut power_saver, &mut state, 2 * POWER_SAVE_TIMEOUT_MS),
+            0
+        );
+        assert_eq!(state.power, PowerState::On);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::{
+    action::Action,
+    common::Duration,
+    event::{Event, EventsCursor},
+    state::State,
+    task::{NextRun, Task, Topics},
+};
+
+use super::{
+    alarm::{Alarm, ALARM_COUNT, WEEKDAYS_ALL, WEEKDAYS_MON_FRI, WEEKDAYS_WEEKEND},
+    stopwatch::{Stopwatch, FIELD_BLINK_MS},
+    touch::{Button, ButtonEvent},
+};
+
+/// The state of the alarm-setting UI, as presented on the display.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct SetAlarmState {
+    pub field: AlarmField,
+    /// The index of the alarm in `state.settings.alarms`.
+    pub index: usize,
+    pub alarm: Alarm,
+    /// The blinking phase of the field being edited.
+    pub is_field_visible: bool,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum AlarmField {
+    Index,
+    Hour,
+    Minute,
+    Days,
+}
+
+/// The days an alarm can be set to ring on, see [`AlarmDays::of()`].
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum AlarmDays {
+    Off,
+    MonFri,
+    All,
+    Weekend,
+}
+
+impl AlarmDays {
+    const ALL: [AlarmDays; 4] = [
+        AlarmDays::Off,
+        AlarmDays::MonFri,
+        AlarmDays::All,
+        AlarmDays::Weekend,
+    ];
+
+    /// The choice matching the alarm, [`AlarmDays::All`] for the custom weekdays.
+    pub fn of(alarm: &Alarm) -> Self {
+        match alarm.weekdays {
+            _ if !alarm.enabled => AlarmDays::Off,
+            WEEKDAYS_MON_FRI => AlarmDays::MonFri,
+            WEEKDAYS_WEEKEND => AlarmDays::Weekend,
+            _ => AlarmDays::All,
+        }
+    }
+
+    fn apply(self, alarm: &mut Alarm) {
+        match self {
+            AlarmDays::Off => alarm.enabled = false,
+            AlarmDays::MonFri => (alarm.enabled, alarm.weekdays) = (true, WEEKDAYS_MON_FRI),
+            AlarmDays::All => (alarm.enabled, alarm.weekdays) = (true, WEEKDAYS_ALL),
+            AlarmDays::Weekend => (alarm.enabled, alarm.weekdays) = (true, WEEKDAYS_WEEKEND),
+        }
+    }
+
+    fn step(alarm: &mut Alarm, by: usize) {
+        let i = Self::ALL
+            .iter()
+            .position(|&days| days == Self::of(alarm))
+            .unwrap();
+        Self::ALL[(i + by) % Self::ALL.len()].apply(alarm);
+    }
+}
+
+/// The alarm-setting mode.
+///
+/// A long press on [`Button::B`] enters the mode. [`Button::B`] and [`Button::C`]
+/// increment and decrement the blinking field, [`Button::A`] moves from the alarm
+/// number to the hour, the minute and the days, and then commits the alarm to
+/// `state.settings`. The mode is abandoned without committing after
+/// [`SET_ALARM_TIMEOUT_MS`] of inactivity.
+#[derive(Default)]
+pub struct SetAlarm {
+    events: EventsCursor,
+    /// The time since the last button event.
+    idle: Stopwatch,
+}
+
+impl Task<State, Action> for SetAlarm {
+    fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        let mut action = None;
+
+        while let Some(event) = state.events.read(&mut self.events) {
+            let Event::Button(event) = event else {
+                continue;
+            };
+            if state.alarm.is_ringing() || state.set_time.is_some() {
+                // The buttons are taken over.
+                continue;
+            }
+            self.idle.start(state.now);
+            action = Self::handle(state, event).or(action);
+        }
+
+        if let Some(set_alarm) = &mut state.set_alarm {
+            if self.idle.has_passed(
+                state.now,
+                Duration::from_ticks(SET_ALARM_TIMEOUT_MS * 1_000),
+            ) {
+                state.set_alarm = None;
+            } else {
+                set_alarm.is_field_visible = self
+                    .idle
+                    .is_blink_visible(state.now, Duration::from_ticks(FIELD_BLINK_MS * 1_000));
+            }
+        }
+
+        (
+            action,
+            NextRun::After(Duration::from_ticks(SET_ALARM_PERIOD_US)),
+        )
+    }
+
+    fn subscriptions(&self) -> Topics {
+        Event::BUTTON
+    }
+}
+
+impl SetAlarm {
+    fn handle(state: &mut State, event: ButtonEvent) -> Option<Action> {
+        let Some(set_alarm) = &mut state.set_alarm else {
+            if event == ButtonEvent::LongPress(Button::B) {
+                state.set_alarm = Some(SetAlarmState {
+                    field: AlarmField::Index,
+                    index: 0,
+                    alarm: state.settings.alarms[0],
+                    is_field_visible: true,
+                });
+            }
+            return None;
+        };
+
+        let alarm = &mut set_alarm.alarm;
+        match (event, set_alarm.field) {
+            (ButtonEvent::Press(Button::A), AlarmField::Index) => {
+                set_alarm.field = AlarmField::Hour;
+            }
+            (ButtonEvent::Press(Button::A), AlarmField::Hour) => {
+                set_alarm.field = AlarmField::Minute;
+            }
+            (ButtonEvent::Press(Button::A), AlarmField::Minute) => {
+                set_alarm.field = AlarmField::Days;
+            }
+            (ButtonEvent::Press(Button::A), AlarmField::Days) => {
+                state.settings.alarms[set_alarm.index] = set_alarm.alarm;
+                state.set_alarm = None;
+                return Some(Action::SaveSettings(state.settings));
+            }
+            (ButtonEvent::Press(button @ (Button::B | Button::C)), field) => {
+                let is_up = button == Button::B;
+                match field {
+                    AlarmField::Index => {
+                        let by = if is_up { 1 } else { ALARM_COUNT - 1 };
+                        set_alarm.index = (set_alarm.index + by) % ALARM_COUNT;
+                        set_alarm.alarm = state.settings.alarms[set_alarm.index];
+                    }
+                    AlarmField::Hour => {
+                        alarm.hour = (alarm.hour + if is_up { 1 } else { 23 }) % 24;
+                    }
+                    AlarmField::Minute => {
+                        alarm.minute = (alarm.minute + if is_up { 1 } else { 59 }) % 60;
+                    }
+                    AlarmField::Days => {
+                        AlarmDays::step(alarm, if is_up { 1 } else { AlarmDays::ALL.len() - 1 });
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+}
+
+const SET_ALARM_PERIOD_US: u64 = 50_000;
+pub const SET_ALARM_TIMEOUT_MS: u64 = 30_000;
+
+#[cfg(test)]
+mod tests {
+    use crate::features::{stopwatch::run_for, touch::press};
+
+    use super::*;
+
+    #[test]
+    fn set_alarm_test() {
+        let mut set_alarm = SetAlarm::default();
+        let mut state = State::default();
+
+        press(
+            &mut set_alarm,
+            &mut state,
+            ButtonEvent::LongPress(Button::B),
+        );
+        press(&mut set_alarm, &mut state, ButtonEvent::Release(Button::B));
+        assert_eq!(
+            state.set_alarm.map(|s| (s.field, s.index)),
+            Some((AlarmField::Index, 0))
+        );
+
+        // The second alarm, at 6:59 every day.
+        for event in [
+            ButtonEvent::Press(Button::B),
+            ButtonEvent::Press(Button::A),
+            ButtonEvent::Press(Button::C),
+            ButtonEvent::Press(Button::A),
+            ButtonEvent::Press(Button::C),
+            ButtonEvent::Press(Button::A),
+            ButtonEvent::Press(Button::B),
+            ButtonEvent::Press(Button::B),
+        ] {
+            assert!(press(&mut set_alarm, &mut state, event).is_none());
+        }
+        assert_eq!(
+            state
+                .set_alarm
+                .map(|s| (s.field, s.index, AlarmDays::of(&s.alarm))),
+            Some((AlarmField::Days, 1, AlarmDays::All))
+        );
+        assert_eq!(state.settings.alarms[1], Alarm::default());
+
+        let action = press(&mut set_alarm, &mut state, ButtonEvent::Press(Button::A));
+        let expected = Alarm {
+            hour: 6,
+            minute: 59,
+            weekdays: WEEKDAYS_ALL,
+            enabled: true,
+        };
+        assert!(matches!(
+            action,
+            Some(Action::SaveSettings(settings)) if settings.alarms[1] == expected
+        ));
+        assert_eq!(state.settings.alarms[1], expected);
+        assert_eq!(state.set_alarm, None);
+    }
+
+    #[test]
+    fn days_test() {
+        let mut alarm = Alarm::default();
+        assert_eq!(AlarmDays::of(&alarm), AlarmDays::Off);
+
+        AlarmDays::step(&mut alarm, 1);
+        assert_eq!(AlarmDays::of(&alarm), AlarmDays::MonFri);
+        AlarmDays::step(&mut alarm, 2);
+        assert_eq!(AlarmDays::of(&alarm), AlarmDays::Weekend);
+        AlarmDays::step(&mut alarm, 1);
+        assert_eq!(AlarmDays::of(&alarm), AlarmDays::Off);
+        assert_eq!(alarm.weekdays, WEEKDAYS_WEEKEND);
+    }
+
+    #[test]
+    fn timeout_test() {
+        let mut set_alarm = SetAlarm::default();
+        let mut state = State::default();
+
+        press(
+            &mut set_alarm,
+            &mut state,
+            ButtonEvent::LongPress(Button::B),
+        );
+        press(&mut set_alarm, &mut state, ButtonEvent::Press(Button::A));
+        press(&mut set_alarm, &mut state, ButtonEvent::Press(Button::B));
+
+        // Woken up by the events of other features, without the time moving on.
+        for _ in 0..1_000 {
+            set_alarm.run(&mut state);
+        }
+        assert!(state.set_alarm.is_some());
+
+        assert!(run_for(
+            &mut set_alarm,
+            &mut state,
+            SET_ALARM_PERIOD_US,
+            SET_ALARM_TIMEOUT_MS
+        )
+        .is_empty());
+        assert_eq!(state.set_alarm, None);
+        assert_eq!(state.settings.alarms[0], Alarm::default());
+    }
+}
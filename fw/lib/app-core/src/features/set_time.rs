@@ -0,0 +1,379 @@
+use crate::{
+    action::Action,
+    calendar,
+    common::Duration,
+    event::{Event, EventsCursor},
+    state::{State, RTC},
+    task::{NextRun, Task, Topics},
+};
+
+use super::{
+    display::HourMode,
+    stopwatch::{Stopwatch, FIELD_BLINK_MS},
+    touch::{Button, ButtonEvent},
+};
+
+pub enum ClockAction {
+    /// Set the wall-clock date and time, resetting the seconds to zero.
+    SetTime {
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+    },
+}
+
+/// The state of the time-setting UI, as presented on the display.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct SetTimeState {
+    pub field: TimeField,
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    /// The blinking phase of the field being edited.
+    pub is_field_visible: bool,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum TimeField {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+}
+
+/// The date and time-setting mode.
+///
+/// A long press on [`Button::A`] enters the mode. [`Button::B`] and [`Button::C`]
+/// increment and decrement the blinking field, [`Button::A`] moves from the year
+/// to the month, the day, the hour and the minute, and then commits the new date
+/// and time. The mode is abandoned without committing after [`SET_TIME_TIMEOUT_MS`]
+/// of inactivity.
+///
+/// Outside of the mode, a long press on [`Button::C`] toggles the 12-hour mode.
+#[derive(Default)]
+pub struct SetTime {
+    events: EventsCursor,
+    /// The time since the last button event.
+    idle: Stopwatch,
+}
+
+impl Task<State, Action> for SetTime {
+    fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        let mut action = None;
+
+        while let Some(event) = state.events.read(&mut self.events) {
+            let Event::Button(event) = event else {
+                continue;
+            };
+            if state.alarm.is_ringing() || state.set_alarm.is_some() {
+                // The buttons are taken over.
+                continue;
+            }
+            self.idle.start(state.now);
+            action = self.handle(state, event).or(action);
+        }
+
+        if let Some(set_time) = &mut state.set_time {
+            if self
+                .idle
+                .has_passed(state.now, Duration::from_ticks(SET_TIME_TIMEOUT_MS * 1_000))
+            {
+                state.set_time = None;
+            } else {
+                set_time.is_field_visible = self
+                    .idle
+                    .is_blink_visible(state.now, Duration::from_ticks(FIELD_BLINK_MS * 1_000));
+            }
+        }
+
+        (
+            action,
+            NextRun::After(Duration::from_ticks(SET_TIME_PERIOD_US)),
+        )
+    }
+
+    fn subscriptions(&self) -> Topics {
+        Event::BUTTON
+    }
+}
+
+impl SetTime {
+    fn handle(&mut self, state: &mut State, event: ButtonEvent) -> Option<Action> {
+        let Some(set_time) = &mut state.set_time else {
+            return match event {
+                ButtonEvent::LongPress(Button::A) => {
+                    let year = state.rtc.year.clamp(YEAR_MIN, YEAR_MAX);
+                    let month = state.rtc.month.clamp(1, 12);
+                    state.set_time = Some(SetTimeState {
+                        field: TimeField::Year,
+                        year,
+                        month,
+                        day: state.rtc.day.clamp(1, calendar::days_in_month(year, month)),
+                        hour: state.rtc.hour,
+                        minute: state.rtc.minute,
+                        is_field_visible: true,
+                    });
+                    None
+                }
+                ButtonEvent::LongPress(Button::C) => {
+                    state.settings.hour_mode = match state.settings.hour_mode {
+                        HourMode::H24 => HourMode::H12,
+                        HourMode::H12 => HourMode::H24,
+                    };
+                    Some(Action::SaveSettings(state.settings))
+                }
+                _ => None,
+            };
+        };
+
+        match (event, set_time.field) {
+            (ButtonEvent::Press(Button::A), TimeField::Year) => {
+                set_time.field = TimeField::Month;
+            }
+            (ButtonEvent::Press(Button::A), TimeField::Month) => {
+                set_time.field = TimeField::Day;
+            }
+            (ButtonEvent::Press(Button::A), TimeField::Day) => {
+                set_time.field = TimeField::Hour;
+            }
+            (ButtonEvent::Press(Button::A), TimeField::Hour) => {
+                set_time.field = TimeField::Minute;
+            }
+            (ButtonEvent::Press(Button::A), TimeField::Minute) => {
+                let SetTimeState {
+                    year,
+                    month,
+                    day,
+                    hour,
+                    minute,
+                    ..
+                } = *set_time;
+                state.set_time = None;
+                state.rtc = RTC {
+                    year,
+                    month,
+                    day,
+                    day_of_week: calendar::day_of_week(year, month, day),
+                    hour,
+                    minute,
+                    second: 0,
+                };
+                return Some(Action::Clock(ClockAction::SetTime {
+                    year,
+                    month,
+                    day,
+                    hour,
+                    minute,
+                }));
+            }
+            (ButtonEvent::Press(button @ (Button::B | Button::C)), field) => {
+                let is_up = button == Button::B;
+                match field {
+                    TimeField::Year => {
+                        set_time.year = step(set_time.year, YEAR_MIN, YEAR_MAX, is_up);
+                    }
+                    TimeField::Month => {
+                        set_time.month = step(set_time.month as u16, 1, 12, is_up) as u8;
+                    }
+                    TimeField::Day => {
+                        let days = calendar::days_in_month(set_time.year, set_time.month);
+                        set_time.day = step(set_time.day as u16, 1, days as u16, is_up) as u8;
+                    }
+                    TimeField::Hour => {
+                        set_time.hour = step(set_time.hour as u16, 0, 23, is_up) as u8;
+                    }
+                    TimeField::Minute => {
+                        set_time.minute = step(set_time.minute as u16, 0, 59, is_up) as u8;
+                    }
+                }
+                // The day may be past the end of the new month.
+                set_time.day = set_time
+                    .day
+                    .min(calendar::days_in_month(set_time.year, set_time.month));
+            }
+            _ => {}
+        }
+
+        None
+    }
+}
+
+/// Step `value` up or down by one, wrapping around within `min..=max`.
+fn step(value: u16, min: u16, max: u16, is_up: bool) -> u16 {
+    let len = max - min + 1;
+    min + (value - min + if is_up { 1 } else { len - 1 }) % len
+}
+
+const SET_TIME_PERIOD_US: u64 = 50_000;
+// The RTC takes every fourth year for a leap year, which holds within these.
+const YEAR_MIN: u16 = 2000;
+const YEAR_MAX: u16 = 2099;
+pub const SET_TIME_TIMEOUT_MS: u64 = 30_000;
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        features::{stopwatch::run_for, touch::press},
+        settings::Settings,
+    };
+
+    use super::*;
+
+    #[test]
+    fn set_time_test() {
+        let mut set_time = SetTime::default();
+        let mut state = State {
+            rtc: RTC {
+                year: 2024,
+                month: 1,
+                day: 31,
+                day_of_week: 3,
+                hour: 23,
+                minute: 58,
+                second: 31,
+            },
+            ..Default::default()
+        };
+        let fields = |state: &State| {
+            state
+                .set_time
+                .map(|s| (s.field, s.year, s.month, s.day, s.hour, s.minute))
+        };
+
+        assert!(press(&mut set_time, &mut state, ButtonEvent::Press(Button::B)).is_none());
+        assert_eq!(state.set_time, None);
+
+        press(&mut set_time, &mut state, ButtonEvent::LongPress(Button::A));
+        press(&mut set_time, &mut state, ButtonEvent::Release(Button::A));
+        assert_eq!(fields(&state), Some((TimeField::Year, 2024, 1, 31, 23, 58)));
+
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::C));
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::A));
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::B));
+        // The day is kept within the month.
+        assert_eq!(
+            fields(&state),
+            Some((TimeField::Month, 2023, 2, 28, 23, 58))
+        );
+
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::A));
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::B));
+        assert_eq!(fields(&state), Some((TimeField::Day, 2023, 2, 1, 23, 58)));
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::C));
+
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::A));
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::B));
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::A));
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::B));
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::B));
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::C));
+        assert_eq!(
+            fields(&state),
+            Some((TimeField::Minute, 2023, 2, 28, 0, 59))
+        );
+
+        let action = press(&mut set_time, &mut state, ButtonEvent::Press(Button::A));
+        assert!(matches!(
+            action,
+            Some(Action::Clock(ClockAction::SetTime {
+                year: 2023,
+                month: 2,
+                day: 28,
+                hour: 0,
+                minute: 59
+            }))
+        ));
+        assert_eq!(state.set_time, None);
+        assert_eq!(
+            (
+                state.rtc.year,
+                state.rtc.month,
+                state.rtc.day,
+                state.rtc.day_of_week
+            ),
+            (2023, 2, 28, 2)
+        );
+        assert_eq!(
+            (state.rtc.hour, state.rtc.minute, state.rtc.second),
+            (0, 59, 0)
+        );
+    }
+
+    #[test]
+    fn hour_mode_test() {
+        let mut set_time = SetTime::default();
+        let mut state = State::default();
+
+        let action = press(&mut set_time, &mut state, ButtonEvent::LongPress(Button::C));
+        assert!(matches!(
+            action,
+            Some(Action::SaveSettings(Settings {
+                hour_mode: HourMode::H12,
+                ..
+            }))
+        ));
+        assert_eq!(state.settings.hour_mode, HourMode::H12);
+
+        press(&mut set_time, &mut state, ButtonEvent::LongPress(Button::C));
+        assert_eq!(state.settings.hour_mode, HourMode::H24);
+    }
+
+    #[test]
+    fn blink_timeout_test() {
+        let mut set_time = SetTime::default();
+        let mut state = State::default();
+        let is_field_visible = |state: &State| state.set_time.map(|s| s.is_field_visible);
+
+        press(&mut set_time, &mut state, ButtonEvent::LongPress(Button::A));
+
+        // Woken up by the events of other features, without the time moving on.
+        for _ in 0..100 {
+            set_time.run(&mut state);
+        }
+        assert_eq!(is_field_visible(&state), Some(true));
+
+        assert!(run_for(
+            &mut set_time,
+            &mut state,
+            SET_TIME_PERIOD_US,
+            FIELD_BLINK_MS
+        )
+        .is_empty());
+        assert_eq!(is_field_visible(&state), Some(false));
+        run_for(
+            &mut set_time,
+            &mut state,
+            SET_TIME_PERIOD_US,
+            FIELD_BLINK_MS,
+        );
+        assert_eq!(is_field_visible(&state), Some(true));
+
+        // A button event starts the blinking and the timeout over.
+        run_for(
+            &mut set_time,
+            &mut state,
+            SET_TIME_PERIOD_US,
+            FIELD_BLINK_MS / 2,
+        );
+        press(&mut set_time, &mut state, ButtonEvent::Press(Button::B));
+        run_for(
+            &mut set_time,
+            &mut state,
+            SET_TIME_PERIOD_US,
+            SET_TIME_TIMEOUT_MS - SET_TIME_PERIOD_US / 1_000,
+        );
+        assert!(state.set_time.is_some());
+        run_for(
+            &mut set_time,
+            &mut state,
+            SET_TIME_PERIOD_US,
+            SET_TIME_PERIOD_US / 1_000,
+        );
+        assert_eq!(state.set_time, None);
+    }
+}
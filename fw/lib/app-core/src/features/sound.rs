@@ -0,0 +1,229 @@
+use crate::{
+    action::Action,
+    common::Duration,
+    event::{Event, EventsCursor},
+    state::State,
+    task::{NextRun, Task, Topics},
+};
+
+use super::touch::ButtonEvent;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum SoundAction {
+    /// Start a square wave of the given frequency in Hz.
+    Tone(u16),
+    Silence,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Note {
+    /// The frequency in Hz, zero for a rest.
+    pub tone_hz: u16,
+    pub duration_ms: u16,
+}
+
+impl Note {
+    pub const fn tone(tone_hz: u16, duration_ms: u16) -> Self {
+        Self {
+            tone_hz,
+            duration_ms,
+        }
+    }
+
+    pub const fn rest(duration_ms: u16) -> Self {
+        Self::tone(0, duration_ms)
+    }
+}
+
+pub struct Melody {
+    pub notes: &'static [Note],
+    pub is_looped: bool,
+}
+
+pub static ALARM_MELODY: Melody = Melody {
+    notes: &[
+        Note::tone(2_048, 100),
+        Note::rest(100),
+        Note::tone(2_048, 100),
+        Note::rest(100),
+        Note::tone(2_048, 100),
+        Note::rest(100),
+        Note::tone(2_048, 100),
+        Note::rest(400),
+    ],
+    is_looped: true,
+};
+
+pub static KEY_CLICK: Melody = Melody {
+    notes: &[Note::tone(4_096, 5)],
+    is_looped: false,
+};
+
+/// Plays melodies note by note, one note per run, so that other tasks keep running in between.
+///
+/// The alarm melody is looped while `state.alarm` is ringing, a key click is played
+/// on every button press otherwise.
+#[derive(Default)]
+pub struct Sequencer {
+    events: EventsCursor,
+    playing: Option<Playing>,
+    is_sounding: bool,
+}
+
+struct Playing {
+    melody: &'static Melody,
+    position: usize,
+}
+
+impl Task<State, Action> for Sequencer {
+    fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        let mut is_pressed = false;
+        while let Some(event) = state.events.read(&mut self.events) {
+            let Event::Button(event) = event else {
+                continue;
+            };
+            is_pressed |= matches!(event, ButtonEvent::Press(_));
+        }
+
+        let is_playing_alarm = matches!(
+            self.playing,
+            Some(Playing { melody, .. }) if core::ptr::eq(melody, &ALARM_MELODY)
+        );
+        if state.alarm.is_ringing() != is_playing_alarm {
+            self.playing = state.alarm.is_ringing().then_some(Playing {
+                melody: &ALARM_MELODY,
+                position: 0,
+            });
+        } else if is_pressed && self.playing.is_none() {
+            self.playing = Some(Playing {
+                melody: &KEY_CLICK,
+                position: 0,
+            });
+        }
+
+        if let Some(note) = self.next_note() {
+            self.is_sounding = note.tone_hz != 0;
+            (
+                Some(Action::Sound(if note.tone_hz != 0 {
+                    SoundAction::Tone(note.tone_hz)
+                } else {
+                    SoundAction::Silence
+                })),
+                NextRun::After(Duration::from_ticks(note.duration_ms as u64 * 1_000)),
+            )
+        } else {
+            let action = if self.is_sounding {
+                self.is_sounding = false;
+                Some(Action::Sound(SoundAction::Silence))
+            } else {
+                None
+            };
+            (
+                action,
+                NextRun::After(Duration::from_ticks(SEQUENCER_IDLE_PERIOD_US)),
+            )
+        }
+    }
+
+    /// The alarm melody starts right away, cutting the note being played short.
+    fn subscriptions(&self) -> Topics {
+        Event::ALARM_FIRED
+    }
+}
+
+impl Sequencer {
+    fn next_note(&mut self) -> Option<Note> {
+        let playing = self.playing.as_mut()?;
+        let notes = playing.melody.notes;
+
+        if playing.position == notes.len() && playing.melody.is_looped {
+            playing.position = 0;
+        }
+
+        if let Some(&note) = notes.get(playing.position) {
+            playing.position += 1;
+            Some(note)
+        } else {
+            self.playing = None;
+            None
+        }
+    }
+}
+
+const SEQUENCER_IDLE_PERIOD_US: u64 = 20_000;
+
+#[cfg(test)]
+mod tests {
+    use crate::features::{
+        alarm::AlarmState,
+        touch::{Button, ButtonEvent},
+    };
+
+    use super::*;
+
+    fn run(sequencer: &mut Sequencer, state: &mut State) -> (Option<SoundAction>, u64) {
+        match sequencer.run(state) {
+            (Some(Action::Sound(action)), NextRun::After(delay)) => {
+                (Some(action), delay.to_millis())
+            }
+            (None, NextRun::After(delay)) => (None, delay.to_millis()),
+            _ => panic!("Unexpected sequencer output"),
+        }
+    }
+
+    #[test]
+    fn key_click_test() {
+        let mut sequencer = Sequencer::default();
+        let mut state = State::default();
+
+        assert_eq!(run(&mut sequencer, &mut state), (None, 20));
+
+        state
+            .events
+            .publish(Event::Button(ButtonEvent::Press(Button::A)));
+        assert_eq!(
+            run(&mut sequencer, &mut state),
+            (Some(SoundAction::Tone(4_096)), 5)
+        );
+        assert_eq!(
+            run(&mut sequencer, &mut state),
+            (Some(SoundAction::Silence), 20)
+        );
+        assert_eq!(run(&mut sequencer, &mut state), (None, 20));
+    }
+
+    #[test]
+    fn alarm_melody_test() {
+        let mut sequencer = Sequencer::default();
+        let mut state = State {
+            alarm: AlarmState::Ringing {
+                index: 0,
+                is_visible: true,
+            },
+            ..Default::default()
+        };
+
+        let notes = (0..ALARM_MELODY.notes.len() * 2)
+            .map(|_| run(&mut sequencer, &mut state))
+            .collect::<Vec<_>>();
+        assert_eq!(notes[0], (Some(SoundAction::Tone(2_048)), 100));
+        assert_eq!(notes[1], (Some(SoundAction::Silence), 100));
+        assert_eq!(
+            notes[..ALARM_MELODY.notes.len()],
+            notes[ALARM_MELODY.notes.len()..]
+        );
+
+        // A press while ringing doesn't interrupt the melody.
+        state
+            .events
+            .publish(Event::Button(ButtonEvent::Press(Button::B)));
+        assert_eq!(notes[0], run(&mut sequencer, &mut state));
+
+        state.alarm = AlarmState::Idle;
+        assert_eq!(
+            run(&mut sequencer, &mut state),
+            (Some(SoundAction::Silence), 20)
+        );
+        assert_eq!(run(&mut sequencer, &mut state), (None, 20));
+    }
+}
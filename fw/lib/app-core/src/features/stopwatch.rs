@@ -0,0 +1,99 @@
+use crate::common::{Duration, Instant};
+#[cfg(test)]
+use crate::{action::Action, state::State, task::Task};
+
+/// Measures the time since an event, such as the last button press, against `state.now`.
+///
+/// The features time their timeouts and their blinking with it rather than by counting
+/// their runs, as a task woken up early by an event runs more often than its period.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Stopwatch {
+    started: Option<Instant>,
+}
+
+impl Stopwatch {
+    /// Starts over from `now`, also when already running.
+    pub fn start(&mut self, now: Instant) {
+        self.started = Some(now);
+    }
+
+    pub fn stop(&mut self) {
+        self.started = None;
+    }
+
+    /// The time since the start, `None` when stopped.
+    pub fn elapsed(&self, now: Instant) -> Option<Duration> {
+        self.started.map(|started| {
+            now.checked_duration_since(started)
+                .unwrap_or(Duration::from_ticks(0))
+        })
+    }
+
+    /// Whether the stopwatch runs and `duration` has not passed since the start.
+    pub fn is_within(&self, now: Instant, duration: Duration) -> bool {
+        matches!(self.elapsed(now), Some(elapsed) if elapsed < duration)
+    }
+
+    /// Whether the stopwatch runs and `duration` has passed since the start.
+    pub fn has_passed(&self, now: Instant, duration: Duration) -> bool {
+        matches!(self.elapsed(now), Some(elapsed) if elapsed >= duration)
+    }
+
+    /// The phase of a blinking visible for the first `half_period` after the start,
+    /// always visible when stopped.
+    pub fn is_blink_visible(&self, now: Instant, half_period: Duration) -> bool {
+        match self.elapsed(now) {
+            Some(elapsed) => (elapsed.ticks() / half_period.ticks()) % 2 == 0,
+            None => true,
+        }
+    }
+}
+
+/// The half-period of the blinking of the field being edited.
+pub const FIELD_BLINK_MS: u64 = 500;
+
+/// Runs the task every `period_us` until `ms` from `state.now`, advancing `state.now`
+/// before each run, and returns the actions.
+#[cfg(test)]
+pub(crate) fn run_for(
+    task: &mut impl Task<State, Action>,
+    state: &mut State,
+    period_us: u64,
+    ms: u64,
+) -> Vec<Action> {
+    (0..ms * 1_000 / period_us)
+        .filter_map(|_| {
+            state.now += Duration::from_ticks(period_us);
+            task.run(state).0
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stopwatch_test() {
+        let mut stopwatch = Stopwatch::default();
+        let second = Duration::from_ticks(1_000_000);
+        let at = |us| Instant::from_ticks(us);
+
+        assert_eq!(stopwatch.elapsed(at(0)), None);
+        assert!(!stopwatch.is_within(at(0), second));
+        assert!(!stopwatch.has_passed(at(0), second));
+        assert!(stopwatch.is_blink_visible(at(700_000), second / 2));
+
+        stopwatch.start(at(1_000_000));
+        assert!(stopwatch.is_within(at(1_999_999), second));
+        assert!(stopwatch.has_passed(at(2_000_000), second));
+        assert!(stopwatch.is_blink_visible(at(1_499_999), second / 2));
+        assert!(!stopwatch.is_blink_visible(at(1_500_000), second / 2));
+        assert!(stopwatch.is_blink_visible(at(2_000_000), second / 2));
+        // A start in the future counts as just started.
+        assert_eq!(stopwatch.elapsed(at(0)), Some(Duration::from_ticks(0)));
+
+        stopwatch.stop();
+        assert_eq!(stopwatch.elapsed(at(2_000_000)), None);
+    }
+}
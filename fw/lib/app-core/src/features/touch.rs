@@ -0,0 +1,227 @@
+use crate::{
+    action::Action,
+    common::Duration,
+    event::Event,
+    state::State,
+    task::{NextRun, Task},
+};
+
+pub const TOUCH_PAD_COUNT: usize = 3;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Button {
+    A,
+    B,
+    C,
+}
+
+impl Button {
+    const ALL: [Button; TOUCH_PAD_COUNT] = [Button::A, Button::B, Button::C];
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ButtonEvent {
+    Press(Button),
+    Release(Button),
+    /// The button has been held for [`LONG_PRESS_MS`]. Emitted once per press, before the release.
+    LongPress(Button),
+}
+
+/// Turns raw pad charge times (`state.touch`) into button events, published to `state.events`.
+///
+/// The raw readings are expected to be refreshed right before each run.
+#[derive(Default)]
+pub struct Touch {
+    pads: [Pad; TOUCH_PAD_COUNT],
+}
+
+impl Task<State, Action> for Touch {
+    fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        for ((pad, &count), button) in self.pads.iter_mut().zip(&state.touch).zip(Button::ALL) {
+            let event = pad.update(count).map(|event| match event {
+                PadEvent::Press => ButtonEvent::Press(button),
+                PadEvent::Release => ButtonEvent::Release(button),
+                PadEvent::LongPress => ButtonEvent::LongPress(button),
+            });
+            if let Some(event) = event {
+                state.events.publish(Event::Button(event));
+            }
+        }
+
+        (None, NextRun::After(Duration::from_ticks(TOUCH_PERIOD_US)))
+    }
+}
+
+#[derive(Default)]
+struct Pad {
+    /// The untouched pad charge time, scaled by `1 << BASELINE_SHIFT`.
+    baseline: Option<i32>,
+    is_pressed: bool,
+    /// The number of consecutive runs contradicting `is_pressed`.
+    debounce: u8,
+    /// The number of runs since the press.
+    held: u16,
+}
+
+enum PadEvent {
+    Press,
+    Release,
+    LongPress,
+}
+
+impl Pad {
+    fn update(&mut self, count: u16) -> Option<PadEvent> {
+        let count = (count as i32) << BASELINE_SHIFT;
+        let baseline = *self.baseline.get_or_insert(count);
+
+        let threshold =
+            (baseline * TOUCH_THRESHOLD_PERCENT / 100).max(TOUCH_THRESHOLD_MIN << BASELINE_SHIFT);
+        let is_touched = if self.is_pressed {
+            // Hysteresis.
+            count > baseline + threshold / 2
+        } else {
+            count > baseline + threshold
+        };
+
+        if is_touched != self.is_pressed {
+            self.debounce += 1;
+            if self.debounce >= DEBOUNCE_RUNS {
+                self.debounce = 0;
+                self.is_pressed = is_touched;
+                self.held = 0;
+                return Some(if is_touched {
+                    PadEvent::Press
+                } else {
+                    PadEvent::Release
+                });
+            }
+        } else {
+            self.debounce = 0;
+        }
+
+        if self.is_pressed {
+            self.held = self.held.saturating_add(1);
+            if self.held == LONG_PRESS_RUNS {
+                return Some(PadEvent::LongPress);
+            } else if self.held >= STUCK_RUNS {
+                // Most likely a baseline shift (moisture, temperature) rather than a finger.
+                self.baseline = Some(count);
+                self.is_pressed = false;
+                return Some(PadEvent::Release);
+            }
+        } else if !is_touched {
+            self.baseline = Some(baseline + ((count - baseline) >> BASELINE_FILTER_SHIFT));
+        }
+
+        None
+    }
+}
+
+const TOUCH_PERIOD_US: u64 = 20_000;
+// A pad state change must be observed for this many runs in a row to be reported.
+const DEBOUNCE_RUNS: u8 = 2;
+pub const LONG_PRESS_MS: u64 = 1_000;
+const LONG_PRESS_RUNS: u16 = (LONG_PRESS_MS * 1_000 / TOUCH_PERIOD_US) as u16;
+// A pad held for this long is considered to have drifted and gets recalibrated.
+const STUCK_RUNS: u16 = (30_000_000 / TOUCH_PERIOD_US) as u16;
+// A touch must increase the pad charge time by at least this much over the baseline.
+const TOUCH_THRESHOLD_PERCENT: i32 = 20;
+const TOUCH_THRESHOLD_MIN: i32 = 4;
+const BASELINE_SHIFT: u32 = 4;
+// The baseline moves by 1/16 of the difference on every untouched run.
+const BASELINE_FILTER_SHIFT: u32 = 4;
+
+/// Publishes the button event and runs the task, for testing the features driven by the buttons.
+#[cfg(test)]
+pub(crate) fn press(
+    task: &mut impl Task<State, Action>,
+    state: &mut State,
+    event: ButtonEvent,
+) -> Option<Action> {
+    state.events.publish(Event::Button(event));
+    task.run(state).0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::EventsCursor;
+
+    use super::*;
+
+    fn run_with(touch: &mut Touch, state: &mut State, counts: [u16; 3], runs: usize) {
+        for _ in 0..runs {
+            state.touch = counts;
+            touch.run(state);
+        }
+    }
+
+    fn events(state: &mut State) -> Vec<ButtonEvent> {
+        // A fresh cursor would see the events already read, hence they are replaced.
+        let mut cursor = EventsCursor::default();
+        let events = core::iter::from_fn(|| state.events.read(&mut cursor))
+            .filter_map(|event| match event {
+                Event::Button(event) => Some(event),
+                _ => None,
+            })
+            .collect();
+        state.events = Default::default();
+        events
+    }
+
+    #[test]
+    fn press_release_test() {
+        let mut touch = Touch::default();
+        let mut state = State::default();
+
+        run_with(&mut touch, &mut state, [100, 100, 100], 10);
+        assert_eq!(events(&mut state), []);
+
+        run_with(&mut touch, &mut state, [100, 150, 100], 1);
+        assert_eq!(events(&mut state), [], "a single sample must be debounced");
+
+        run_with(&mut touch, &mut state, [100, 150, 100], 1);
+        assert_eq!(events(&mut state), [ButtonEvent::Press(Button::B)]);
+
+        run_with(&mut touch, &mut state, [100, 100, 100], 2);
+        assert_eq!(events(&mut state), [ButtonEvent::Release(Button::B)]);
+    }
+
+    #[test]
+    fn long_press_test() {
+        let mut touch = Touch::default();
+        let mut state = State::default();
+
+        run_with(&mut touch, &mut state, [100, 100, 100], 10);
+        run_with(
+            &mut touch,
+            &mut state,
+            [150, 100, 100],
+            2 + LONG_PRESS_RUNS as usize,
+        );
+        run_with(&mut touch, &mut state, [100, 100, 100], 2);
+
+        assert_eq!(
+            events(&mut state),
+            [
+                ButtonEvent::Press(Button::A),
+                ButtonEvent::LongPress(Button::A),
+                ButtonEvent::Release(Button::A),
+            ]
+        );
+    }
+
+    #[test]
+    fn baseline_drift_test() {
+        let mut touch = Touch::default();
+        let mut state = State::default();
+
+        // A slow drift must not be reported as a touch.
+        for count in 100..160 {
+            run_with(&mut touch, &mut state, [100, 100, count], 4);
+        }
+        assert_eq!(events(&mut state), []);
+
+        run_with(&mut touch, &mut state, [100, 100, 220], 2);
+        assert_eq!(events(&mut state), [ButtonEvent::Press(Button::C)]);
+    }
+}
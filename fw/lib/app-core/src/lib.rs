@@ -1,7 +1,12 @@
 #![cfg_attr(not(test), no_std)]
 
 pub mod action;
+#[cfg(any(test, feature = "battery-model"))]
+pub mod battery_model;
+pub mod calendar;
 pub mod common;
+pub mod event;
 pub mod features;
+pub mod settings;
 pub mod state;
 pub mod task;
@@ -0,0 +1,33 @@
+use crate::features::{
+    alarm::{Alarm, ALARM_COUNT},
+    brightness::NightWindow,
+    display::{DateFormat, HourMode},
+};
+
+/// Settings persisted across resets.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Settings {
+    /// Charger calibration: the per-cell gain correcting the battery voltage measurement
+    /// for the ADC reference and voltage divider tolerances.
+    pub bat_voltage_gain: (f32, f32),
+    pub hour_mode: HourMode,
+    pub date_format: DateFormat,
+    /// Show the date for a few seconds every minute.
+    pub show_date_periodically: bool,
+    /// The display is dimmed during the night window.
+    pub night_window: NightWindow,
+    pub alarms: [Alarm; ALARM_COUNT],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            bat_voltage_gain: (1.0, 1.0),
+            hour_mode: HourMode::H24,
+            date_format: DateFormat::DayMonth,
+            show_date_periodically: true,
+            night_window: Default::default(),
+            alarms: Default::default(),
+        }
+    }
+}
@@ -1,25 +1,76 @@
-use crate::features::charger::BatteryState;
+use crate::{
+    common::Instant,
+    event::{Event, Events},
+    features::{
+        alarm::AlarmState, charger::BatteryState, display::View, power::PowerState,
+        set_alarm::SetAlarmState, set_time::SetTimeState, touch::TOUCH_PAD_COUNT,
+    },
+    settings::Settings,
+};
 
 pub struct State {
+    /// The scheduler time, updated before every run.
+    pub now: Instant,
     pub rtc: RTC,
+    pub settings: Settings,
+    /// Set through [`Self::set_ext_power()`].
     pub ext_power: bool,
     pub bat_voltage: (f32, f32),
+    /// The battery temperature in °C, if measured.
+    pub bat_temperature: Option<f32>,
     pub bat_level: BatteryState,
+    /// Raw touch pad charge times, in arbitrary units.
+    pub touch: [u16; TOUCH_PAD_COUNT],
+    pub events: Events,
+    /// The time-setting UI state, if the time is being set.
+    pub set_time: Option<SetTimeState>,
+    /// The alarm-setting UI state, if an alarm is being set.
+    pub set_alarm: Option<SetAlarmState>,
+    pub view: View,
+    pub alarm: AlarmState,
+    pub power: PowerState,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
+            now: Instant::from_ticks(0),
             rtc: Default::default(),
+            settings: Default::default(),
             ext_power: false,
             bat_voltage: (0.0, 0.0),
+            bat_temperature: None,
             bat_level: BatteryState::AboveNominal,
+            touch: [0; TOUCH_PAD_COUNT],
+            events: Default::default(),
+            set_time: None,
+            set_alarm: None,
+            view: View::Time,
+            alarm: AlarmState::Idle,
+            power: PowerState::On,
+        }
+    }
+}
+
+impl State {
+    /// Update `ext_power` from a fresh sample, publishing [`Event::ExtPower`] when it changes.
+    pub fn set_ext_power(&mut self, ext_power: bool) {
+        if ext_power != self.ext_power {
+            self.ext_power = ext_power;
+            self.events.publish(Event::ExtPower(ext_power));
         }
     }
 }
 
 #[derive(Default)]
 pub struct RTC {
+    pub year: u16,
+    /// 1..=12
+    pub month: u8,
+    /// 1..=31
+    pub day: u8,
+    /// 0..=6, 0 is Sunday.
+    pub day_of_week: u8,
     pub hour: u8,
     pub minute: u8,
     pub second: u8,
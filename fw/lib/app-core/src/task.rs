@@ -1,4 +1,10 @@
+mod action_sink;
+mod async_task;
+#[cfg(all(test, feature = "alloc"))]
+pub(crate) mod harness;
 pub mod scheduler;
 mod task;
 
+pub use action_sink::*;
+pub use async_task::*;
 pub use task::*;
@@ -0,0 +1,78 @@
+/// The receiver of the actions emitted by a task run, see [`MultiActionTask`].
+///
+/// [`MultiActionTask`]: super::MultiActionTask
+pub trait ActionSink<Action> {
+    fn push(&mut self, action: Action);
+}
+
+impl<Action, F> ActionSink<Action> for F
+where
+    F: FnMut(Action),
+{
+    fn push(&mut self, action: Action) {
+        self(action)
+    }
+}
+
+/// A fixed-capacity [`ActionSink`] to be drained after every run.
+///
+/// The actions pushed while the buffer is full are dropped and counted.
+pub struct ActionBuffer<Action, const N: usize> {
+    actions: [Option<Action>; N],
+    len: usize,
+    dropped: u32,
+}
+
+impl<Action, const N: usize> Default for ActionBuffer<Action, N> {
+    fn default() -> Self {
+        Self {
+            actions: core::array::from_fn(|_| None),
+            len: 0,
+            dropped: 0,
+        }
+    }
+}
+
+impl<Action, const N: usize> ActionBuffer<Action, N> {
+    /// Take the actions out, in the order they were pushed.
+    pub fn drain(&mut self) -> impl Iterator<Item = Action> + '_ {
+        let len = core::mem::take(&mut self.len);
+        self.actions[..len].iter_mut().filter_map(Option::take)
+    }
+
+    /// The number of actions dropped so far, because the buffer was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+impl<Action, const N: usize> ActionSink<Action> for ActionBuffer<Action, N> {
+    fn push(&mut self, action: Action) {
+        if let Some(slot) = self.actions.get_mut(self.len) {
+            *slot = Some(action);
+            self.len += 1;
+        } else {
+            self.dropped = self.dropped.saturating_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_buffer_test() {
+        let mut buffer = ActionBuffer::<i32, 2>::default();
+
+        buffer.push(1);
+        buffer.push(2);
+        buffer.push(3);
+        assert_eq!(buffer.drain().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(buffer.dropped(), 1);
+
+        buffer.push(4);
+        assert_eq!(buffer.drain().collect::<Vec<_>>(), [4]);
+        assert_eq!(buffer.drain().count(), 0);
+    }
+}
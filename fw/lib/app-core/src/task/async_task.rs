@@ -0,0 +1,239 @@
+use core::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    ptr,
+    task::{Context, Poll, Waker},
+};
+
+use crate::common::{Duration, Instant};
+
+use super::{NextRun, Task};
+
+/// What the futures of the [`AsyncTask`]s reach the rest of the app through.
+///
+/// The tasks run one at a time, so one instance can be shared by all of them.
+pub struct AsyncIo<'a, State, Action> {
+    clock: &'a dyn Fn() -> Instant,
+    /// The state of the running task, null in between the runs.
+    state: Cell<*mut State>,
+    /// The action emitted by the running task.
+    action: Cell<Option<Action>>,
+    /// The earliest instant a pending timer of the running task is due at.
+    wake_at: Cell<Option<Instant>>,
+}
+
+impl<'a, State, Action> AsyncIo<'a, State, Action> {
+    pub fn new(clock: &'a dyn Fn() -> Instant) -> Self {
+        Self {
+            clock,
+            state: Cell::new(ptr::null_mut()),
+            action: Cell::new(None),
+            wake_at: Cell::new(None),
+        }
+    }
+
+    pub fn now(&self) -> Instant {
+        (self.clock)()
+    }
+
+    /// Call `f` with the state of the running task.
+    ///
+    /// Panics when nested, as the state would be borrowed twice.
+    pub fn with_state<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        let state = self.state.replace(ptr::null_mut());
+        assert!(
+            !state.is_null(),
+            "the state is only available to the running task, once at a time"
+        );
+
+        // Set from a `&mut State` for the duration of the run, see `AsyncTask::run()`.
+        let result = f(unsafe { &mut *state });
+        self.state.set(state);
+
+        result
+    }
+
+    /// Emit an action, passed on to the scheduler at the end of the run.
+    ///
+    /// A run emits one action at most, the next one waits for the following run, which
+    /// is due right away.
+    pub fn emit(&self, action: Action) -> Emit<'_, 'a, State, Action> {
+        Emit {
+            io: self,
+            action: Some(action),
+        }
+    }
+
+    /// Complete after the given delay.
+    pub fn sleep(&self, delay: Duration) -> Sleep<'_, 'a, State, Action> {
+        self.sleep_until(self.now() + delay)
+    }
+
+    /// Complete at the given instant, the task is scheduled to run again by then.
+    pub fn sleep_until(&self, at: Instant) -> Sleep<'_, 'a, State, Action> {
+        Sleep { io: self, at }
+    }
+
+    /// Make sure the running task runs again by the given instant.
+    fn wake_by(&self, at: Instant) {
+        let wake_at = self.wake_at.get();
+        self.wake_at
+            .set(Some(wake_at.map_or(at, |wake_at| wake_at.min(at))));
+    }
+}
+
+/// The future of [`AsyncIo::sleep()`] and [`AsyncIo::sleep_until()`].
+pub struct Sleep<'io, 'a, State, Action> {
+    io: &'io AsyncIo<'a, State, Action>,
+    at: Instant,
+}
+
+impl<State, Action> Future for Sleep<'_, '_, State, Action> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        if self.io.now() >= self.at {
+            Poll::Ready(())
+        } else {
+            self.io.wake_by(self.at);
+            Poll::Pending
+        }
+    }
+}
+
+/// The future of [`AsyncIo::emit()`].
+pub struct Emit<'io, 'a, State, Action> {
+    io: &'io AsyncIo<'a, State, Action>,
+    action: Option<Action>,
+}
+
+// The action is moved out as a whole, never pinned.
+impl<State, Action> Unpin for Emit<'_, '_, State, Action> {}
+
+impl<State, Action> Future for Emit<'_, '_, State, Action> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let emitted = this.io.action.take();
+        if emitted.is_none() {
+            this.io.action.set(this.action.take());
+            Poll::Ready(())
+        } else {
+            this.io.action.set(emitted);
+            this.io.wake_by(this.io.now());
+            Poll::Pending
+        }
+    }
+}
+
+/// A task driving a future, for the features easier to write as `async` code.
+///
+/// The future is polled on every run. While it waits for a timer of [`AsyncIo`], the task
+/// asks the scheduler for the run to be delayed until the timer is due, so the timed
+/// and the `async` tasks share the same scheduler. While it waits for anything else,
+/// or once it has completed, it is polled every [`ASYNC_IDLE_PERIOD_US`].
+///
+/// Like any [`Task`], a run emits one action at most, see [`AsyncIo::emit()`].
+pub struct AsyncTask<'a, State, Action> {
+    io: &'a AsyncIo<'a, State, Action>,
+    future: Option<Pin<&'a mut dyn Future<Output = ()>>>,
+}
+
+impl<'a, State, Action> AsyncTask<'a, State, Action> {
+    /// Drive the future pinned by the caller, for example with [`core::pin::pin!`].
+    pub fn new(
+        io: &'a AsyncIo<'a, State, Action>,
+        future: Pin<&'a mut dyn Future<Output = ()>>,
+    ) -> Self {
+        Self {
+            io,
+            future: Some(future),
+        }
+    }
+}
+
+impl<State, Action> Task<State, Action> for AsyncTask<'_, State, Action> {
+    fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        let idle = NextRun::After(Duration::from_ticks(ASYNC_IDLE_PERIOD_US));
+        let Some(future) = &mut self.future else {
+            return (None, idle);
+        };
+
+        self.io.state.set(state);
+        self.io.wake_at.set(None);
+        let poll = future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()));
+        self.io.state.set(ptr::null_mut());
+
+        let next_run = match (poll, self.io.wake_at.take()) {
+            (Poll::Pending, Some(wake_at)) => NextRun::After(
+                wake_at
+                    .checked_duration_since(self.io.now())
+                    .unwrap_or(Duration::from_ticks(0)),
+            ),
+            (Poll::Pending, None) => idle,
+            (Poll::Ready(()), _) => {
+                self.future = None;
+                idle
+            }
+        };
+
+        (self.io.action.take(), next_run)
+    }
+}
+
+pub const ASYNC_IDLE_PERIOD_US: u64 = 20_000;
+
+#[cfg(test)]
+mod tests {
+    use core::pin::pin;
+
+    use crate::task::{scheduler::StaticScheduler, InlineFnTask};
+
+    use super::*;
+
+    #[test]
+    fn async_task_test() {
+        let now = Cell::new(Instant::from_ticks(0));
+        let clock = || now.get();
+        let io = AsyncIo::new(&clock);
+
+        let future = pin!(async {
+            loop {
+                io.with_state(|log: &mut Vec<&str>| log.push("async"));
+                io.emit(1).await;
+                io.sleep(Duration::from_ticks(300)).await;
+                io.emit(2).await;
+                io.emit(3).await;
+                io.sleep(Duration::from_ticks(100)).await;
+            }
+        });
+        let mut async_task = AsyncTask::new(&io, future);
+        let mut fn_task = InlineFnTask::new(|log: &mut Vec<&str>| {
+            log.push("fn");
+            (None, NextRun::After(Duration::from_ticks(200)))
+        });
+        let mut scheduler = StaticScheduler::new([&mut async_task as _, &mut fn_task as _]);
+        let mut log = Vec::new();
+        let mut run = |at: u64, log: &mut Vec<&'static str>| {
+            now.set(Instant::from_ticks(at));
+            let mut actions = Vec::new();
+            scheduler.run(now.get(), log, &mut |action| actions.push(action));
+            actions
+        };
+
+        assert_eq!(run(0, &mut log), [1]);
+        assert_eq!(run(0, &mut log), []);
+        assert_eq!(log, ["async", "fn"]);
+
+        // Not due yet, the fn task is.
+        assert_eq!(run(200, &mut log), []);
+        assert_eq!(run(300, &mut log), [2]);
+        assert_eq!(run(300, &mut log), [3]);
+        assert_eq!(run(400, &mut log), [1]);
+        assert_eq!(log, ["async", "fn", "fn", "async"]);
+    }
+}
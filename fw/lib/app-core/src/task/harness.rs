@@ -0,0 +1,270 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    common::{Duration, Instant},
+    task::{
+        scheduler::{Deadline, Scheduler},
+        MultiActionTask, NextRun, Priority, Task, Topics,
+    },
+};
+
+/// Steps a [`Scheduler`] through a simulated timeline, recording every task run.
+///
+/// Every run takes the simulated cost of its task, the time jumps to the next deadline
+/// when no task is due.
+pub struct Harness<State, Action> {
+    scheduler: Scheduler<State, Action>,
+    log: Rc<RefCell<Log<Action>>>,
+    /// The run cost of every task, by the task index.
+    costs: Vec<Duration>,
+}
+
+/// A task run.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Run {
+    /// The task index, in the order the tasks were added.
+    pub task: usize,
+    pub at: Instant,
+    pub next_run: NextRun,
+}
+
+struct Log<Action> {
+    now: Instant,
+    runs: Vec<Run>,
+    actions: Vec<(Instant, Action)>,
+}
+
+impl<State, Action: 'static> Harness<State, Action> {
+    pub fn new() -> Self {
+        Self {
+            scheduler: Scheduler::new([]),
+            log: Rc::new(RefCell::new(Log {
+                now: Instant::from_ticks(0),
+                runs: Vec::new(),
+                actions: Vec::new(),
+            })),
+            costs: Vec::new(),
+        }
+    }
+
+    /// Add a task taking `cost` per run, return its index.
+    pub fn add(
+        &mut self,
+        task: impl MultiActionTask<State, Action> + 'static,
+        cost: Duration,
+    ) -> usize
+    where
+        State: 'static,
+    {
+        let index = self.costs.len();
+        self.costs.push(cost);
+        self.scheduler.add(Box::new(Recorded {
+            index,
+            task: Box::new(task),
+            log: self.log.clone(),
+        }));
+
+        index
+    }
+
+    pub fn now(&self) -> Instant {
+        self.log.borrow().now
+    }
+
+    /// Run the next task due, or wait for one.
+    pub fn step(&mut self, state: &mut State) {
+        let now = self.now();
+        let run_count = self.log.borrow().runs.len();
+
+        self.scheduler.run(now, state, &mut |_| {});
+
+        let ran = self.log.borrow().runs.get(run_count).map(|run| run.task);
+        self.log.borrow_mut().now = match ran {
+            Some(task) => now + self.costs[task],
+            None => match self.scheduler.next_deadline() {
+                Deadline::At(deadline) => deadline,
+                Deadline::Now | Deadline::Never => now + Duration::from_ticks(1),
+            },
+        };
+    }
+
+    pub fn run_until(&mut self, end: Instant, state: &mut State) {
+        while self.now() < end {
+            self.step(state);
+        }
+    }
+
+    pub fn runs(&self) -> Vec<Run> {
+        self.log.borrow().runs.clone()
+    }
+
+    /// Take the actions emitted so far, with the instants of the runs emitting them.
+    pub fn take_actions(&mut self) -> Vec<(Instant, Action)> {
+        core::mem::take(&mut self.log.borrow_mut().actions)
+    }
+
+    /// The longest delay of a task run past the deadline the task asked for.
+    pub fn max_lateness(&self, task: usize) -> Duration {
+        let runs = self.runs();
+        let mut runs = runs.iter().filter(|run| run.task == task);
+        let Some(mut previous) = runs.next() else {
+            return Duration::from_ticks(0);
+        };
+
+        let mut max_lateness = Duration::from_ticks(0);
+        for run in runs {
+            if let NextRun::After(delay) = previous.next_run {
+                let lateness = run
+                    .at
+                    .checked_duration_since(previous.at + delay)
+                    .unwrap_or(Duration::from_ticks(0));
+                max_lateness = max_lateness.max(lateness);
+            }
+            previous = run;
+        }
+
+        max_lateness
+    }
+}
+
+/// Logs the runs of the wrapped task.
+///
+/// The actions go to the log too, as a [`MultiActionTask`] can't be wrapped by another one.
+struct Recorded<State, Action> {
+    index: usize,
+    task: Box<dyn MultiActionTask<State, Action>>,
+    log: Rc<RefCell<Log<Action>>>,
+}
+
+impl<State, Action> Task<State, Action> for Recorded<State, Action> {
+    fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        let at = self.log.borrow().now;
+        let mut actions = Vec::new();
+        let next_run = self
+            .task
+            .run(state, &mut |action| actions.push((at, action)));
+
+        let mut log = self.log.borrow_mut();
+        log.runs.push(Run {
+            task: self.index,
+            at,
+            next_run,
+        });
+        log.actions.extend(actions);
+
+        (None, next_run)
+    }
+
+    fn priority(&self) -> Priority {
+        self.task.priority()
+    }
+
+    fn subscriptions(&self) -> Topics {
+        self.task.subscriptions()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::Action,
+        features::{
+            alarm::AlarmClock, charger::Charger, date_view::DateView, display::Display,
+            power::PowerSaver, set_time::SetTime, touch::Touch,
+        },
+        state::State,
+        task::FnTask,
+    };
+
+    use super::*;
+
+    fn fn_task(next_run: NextRun) -> FnTask<(), ()> {
+        FnTask::new(move |_: &mut ()| (None, next_run))
+    }
+
+    #[test]
+    fn round_robin_test() {
+        let mut harness = Harness::new();
+        let in_order = [
+            harness.add(fn_task(NextRun::InOrder), Duration::from_ticks(30)),
+            harness.add(fn_task(NextRun::InOrder), Duration::from_ticks(10)),
+            harness.add(fn_task(NextRun::InOrder), Duration::from_ticks(20)),
+        ];
+        harness.add(
+            fn_task(NextRun::After(Duration::from_ticks(100))),
+            Duration::from_ticks(10),
+        );
+
+        harness.run_until(Instant::from_ticks(10_000), &mut ());
+
+        let runs = harness
+            .runs()
+            .iter()
+            .map(|run| run.task)
+            .filter(|task| in_order.contains(task))
+            .collect::<Vec<_>>();
+        assert!(runs.len() > 100);
+        for (i, &task) in runs.iter().enumerate() {
+            assert_eq!(task, in_order[i % in_order.len()]);
+        }
+    }
+
+    #[test]
+    fn timed_test() {
+        let mut harness = Harness::new();
+        let slow = harness.add(
+            fn_task(NextRun::After(Duration::from_ticks(1_000))),
+            Duration::from_ticks(200),
+        );
+        let fast = harness.add(
+            fn_task(NextRun::After(Duration::from_ticks(300))),
+            Duration::from_ticks(10),
+        );
+
+        harness.run_until(Instant::from_ticks(10_000), &mut ());
+
+        // The time jumps to the deadlines, a task is only late while another one is running.
+        let runs = harness.runs();
+        assert_eq!(
+            runs[..3]
+                .iter()
+                .map(|run| run.at.ticks())
+                .collect::<Vec<_>>(),
+            [0, 200, 500]
+        );
+        assert!(harness.max_lateness(slow) <= Duration::from_ticks(10));
+        assert!(harness.max_lateness(fast) > Duration::from_ticks(0));
+        assert!(harness.max_lateness(fast) <= Duration::from_ticks(200));
+    }
+
+    #[test]
+    fn display_lateness_test() {
+        const SLOWEST_US: u64 = 300;
+
+        let mut harness = Harness::<State, Action>::new();
+        let display = harness.add(Display::default(), Duration::from_ticks(15));
+        harness.add(Charger::default(), Duration::from_ticks(SLOWEST_US));
+        harness.add(Touch::default(), Duration::from_ticks(150));
+        harness.add(SetTime::default(), Duration::from_ticks(20));
+        harness.add(DateView::default(), Duration::from_ticks(20));
+        harness.add(AlarmClock::default(), Duration::from_ticks(20));
+        harness.add(PowerSaver::default(), Duration::from_ticks(10));
+        let mut state = State {
+            ext_power: true,
+            ..Default::default()
+        };
+
+        harness.run_until(Instant::from_ticks(10_000_000), &mut state);
+
+        // The display runs first when due, it only waits for the task already running.
+        assert!(harness.max_lateness(display) <= Duration::from_ticks(SLOWEST_US));
+        assert!(
+            harness
+                .take_actions()
+                .iter()
+                .filter(|(_, action)| matches!(action, Action::Display(_)))
+                .count()
+                > 1_000
+        );
+    }
+}
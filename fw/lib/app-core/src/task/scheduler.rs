@@ -1,80 +1,312 @@
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
-use core::cmp::Ordering;
+use core::{cmp::Reverse, ops::DerefMut};
 
+#[cfg(feature = "alloc")]
 use alloc::{boxed::Box, vec::Vec};
 
 use crate::{
-    common::Instant,
-    task::{NextRun, Task},
+    common::{Duration, Instant},
+    task::{ActionSink, MultiActionTask, NextRun, Priority, Topics},
 };
 
+#[cfg(feature = "alloc")]
+type BoxedTask<State, Action> = Box<dyn MultiActionTask<State, Action>>;
+type TaskRef<'a, State, Action> = &'a mut dyn MultiActionTask<State, Action>;
+
+/// A scheduler owning a growable list of boxed tasks.
+#[cfg(feature = "alloc")]
 pub struct Scheduler<State, Action> {
-    tasks: Vec<Scheduled<Box<dyn Task<State, Action>>>>,
-    next_in_order: usize,
+    core: Core<Vec<Scheduled<BoxedTask<State, Action>>>>,
+    next_handle: TaskHandle,
 }
 
+#[cfg(feature = "alloc")]
 impl<State, Action> Scheduler<State, Action> {
-    pub fn new(tasks: impl IntoIterator<Item = Box<dyn Task<State, Action>>>) -> Self {
+    pub fn new(tasks: impl IntoIterator<Item = BoxedTask<State, Action>>) -> Self {
+        let mut scheduler = Self {
+            core: Core::new(Vec::new()),
+            next_handle: TaskHandle(0),
+        };
+        for task in tasks {
+            scheduler.add(task);
+        }
+
+        scheduler
+    }
+
+    /// Add a task after the others, due in order until it asks for a delay.
+    pub fn add(&mut self, task: BoxedTask<State, Action>) -> TaskHandle {
+        let handle = self.next_handle;
+        self.next_handle = TaskHandle(handle.0 + 1);
+        self.core.tasks.push(Scheduled::new(handle, task));
+
+        handle
+    }
+
+    /// Remove the task, returning it.
+    ///
+    /// The task to run next in order stays the same, or becomes the one following the removed task
+    /// if it was the removed task itself.
+    pub fn remove(&mut self, handle: TaskHandle) -> Result<BoxedTask<State, Action>, UnknownTask> {
+        let i = self.core.find(handle)?;
+        let removed = self.core.tasks.remove(i);
+
+        if i < self.core.next_in_order {
+            self.core.next_in_order -= 1;
+        }
+        if self.core.next_in_order >= self.core.tasks.len() {
+            self.core.next_in_order = 0;
+        }
+
+        Ok(removed.task)
+    }
+
+    /// Return the handles of the tasks, in the order they were added.
+    pub fn handles(&self) -> impl Iterator<Item = TaskHandle> + '_ {
+        self.core.tasks.iter().map(|scheduled| scheduled.handle)
+    }
+
+    /// Skip the task until it's resumed, see [`Self::resume()`].
+    pub fn suspend(&mut self, handle: TaskHandle) -> Result<(), UnknownTask> {
+        self.core.suspend(handle)
+    }
+
+    /// Let the task run again, right away if its run fell due in the meantime.
+    pub fn resume(&mut self, handle: TaskHandle) -> Result<(), UnknownTask> {
+        self.core.resume(handle)
+    }
+
+    /// Make the task due at the given instant, whatever it asked for on its last run.
+    pub fn reschedule(&mut self, handle: TaskHandle, at: Instant) -> Result<(), UnknownTask> {
+        self.core.reschedule(handle, at)
+    }
+
+    /// Run the next task due, pushing the actions it emits into `actions`.
+    pub fn run(&mut self, now: Instant, state: &mut State, actions: &mut impl ActionSink<Action>) {
+        self.core.run(now, state, actions)
+    }
+
+    /// Make the timed tasks subscribed to any of the topics due now, unless they are already.
+    pub fn wake(&mut self, now: Instant, topics: Topics) {
+        self.core.wake(now, topics)
+    }
+
+    /// Return when the next task is due, so that the caller can sleep until then.
+    ///
+    /// The tasks running in order are always due, see [`Deadline`].
+    pub fn next_deadline(&self) -> Deadline {
+        self.core.next_deadline()
+    }
+
+    /// Return the run statistics of the tasks, in the order they were added.
+    pub fn stats(&self) -> impl Iterator<Item = &TaskStats> + '_ {
+        self.core.stats()
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.core.reset_stats()
+    }
+}
+
+/// A heap-free scheduler of `N` tasks borrowed for its lifetime, otherwise the same as `Scheduler`.
+pub struct StaticScheduler<'a, State, Action, const N: usize> {
+    core: Core<[Scheduled<TaskRef<'a, State, Action>>; N]>,
+}
+
+impl<'a, State, Action, const N: usize> StaticScheduler<'a, State, Action, N> {
+    pub fn new(tasks: [TaskRef<'a, State, Action>; N]) -> Self {
+        let mut handle = TaskHandle(0);
+        Self {
+            core: Core::new(tasks.map(|task| {
+                let scheduled = Scheduled::new(handle, task);
+                handle = TaskHandle(handle.0 + 1);
+                scheduled
+            })),
+        }
+    }
+
+    /// Return the handles of the tasks, in the order they were passed to [`Self::new()`].
+    pub fn handles(&self) -> [TaskHandle; N] {
+        core::array::from_fn(|i| self.core.tasks[i].handle)
+    }
+
+    /// Skip the task until it's resumed, see [`Self::resume()`].
+    pub fn suspend(&mut self, handle: TaskHandle) -> Result<(), UnknownTask> {
+        self.core.suspend(handle)
+    }
+
+    /// Let the task run again, right away if its run fell due in the meantime.
+    pub fn resume(&mut self, handle: TaskHandle) -> Result<(), UnknownTask> {
+        self.core.resume(handle)
+    }
+
+    /// Make the task due at the given instant, whatever it asked for on its last run.
+    pub fn reschedule(&mut self, handle: TaskHandle, at: Instant) -> Result<(), UnknownTask> {
+        self.core.reschedule(handle, at)
+    }
+
+    /// Run the next task due, pushing the actions it emits into `actions`.
+    pub fn run(&mut self, now: Instant, state: &mut State, actions: &mut impl ActionSink<Action>) {
+        self.core.run(now, state, actions)
+    }
+
+    /// Make the timed tasks subscribed to any of the topics due now, unless they are already.
+    pub fn wake(&mut self, now: Instant, topics: Topics) {
+        self.core.wake(now, topics)
+    }
+
+    /// Return when the next task is due, so that the caller can sleep until then.
+    ///
+    /// The tasks running in order are always due, see [`Deadline`].
+    pub fn next_deadline(&self) -> Deadline {
+        self.core.next_deadline()
+    }
+
+    /// Return the run statistics of the tasks, in the order they were passed to [`Self::new()`].
+    pub fn stats(&self) -> [TaskStats; N] {
+        core::array::from_fn(|i| self.core.tasks[i].stats)
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.core.reset_stats()
+    }
+}
+
+/// The scheduling shared by the schedulers, whatever the tasks are stored in.
+struct Core<Tasks> {
+    tasks: Tasks,
+    next_in_order: usize,
+}
+
+impl<Tasks: TaskList> Core<Tasks> {
+    fn new(tasks: Tasks) -> Self {
         Self {
-            tasks: tasks
-                .into_iter()
-                .map(|task| Scheduled {
-                    at: SchedulePoint::InOrder,
-                    task,
-                })
-                .collect::<Vec<_>>(),
+            tasks,
             next_in_order: 0,
         }
     }
 
-    pub fn run(&mut self, now: Instant, state: &mut State) -> Option<Action> {
+    fn run<State, Action, T>(
+        &mut self,
+        now: Instant,
+        state: &mut State,
+        actions: &mut impl ActionSink<Action>,
+    ) where
+        Tasks::Task: DerefMut<Target = T>,
+        T: MultiActionTask<State, Action> + ?Sized,
+    {
         if let Some(timed) = self.pick_task(now) {
-            let (action, next_run) = timed.task.run(state);
+            timed.stats.record(now, timed.at);
+
+            let next_run = timed.task.run(state, actions);
             timed.at = match next_run {
                 NextRun::InOrder => SchedulePoint::InOrder,
                 NextRun::After(delay) => SchedulePoint::At(now + delay),
             };
+        }
+    }
 
-            action
-        } else {
-            None
+    fn wake(&mut self, now: Instant, topics: Topics) {
+        for scheduled in self.tasks.as_mut_slice() {
+            if let SchedulePoint::At(at) = scheduled.at {
+                if at > now && scheduled.subscriptions.intersects(topics) {
+                    scheduled.at = SchedulePoint::At(now);
+                }
+            }
         }
     }
 
-    fn pick_task(&mut self, now: Instant) -> Option<&mut Scheduled<Box<dyn Task<State, Action>>>> {
+    fn next_deadline(&self) -> Deadline {
+        self.tasks
+            .as_slice()
+            .iter()
+            .filter(|scheduled| !scheduled.is_suspended)
+            .fold(Deadline::Never, |deadline, scheduled| {
+                match (deadline, scheduled.at) {
+                    (Deadline::Now, _) | (_, SchedulePoint::InOrder) => Deadline::Now,
+                    (Deadline::At(deadline), SchedulePoint::At(at)) => {
+                        Deadline::At(deadline.min(at))
+                    }
+                    (Deadline::Never, SchedulePoint::At(at)) => Deadline::At(at),
+                }
+            })
+    }
+
+    #[cfg(feature = "alloc")]
+    fn stats(&self) -> impl Iterator<Item = &TaskStats> + '_ {
+        self.tasks
+            .as_slice()
+            .iter()
+            .map(|scheduled| &scheduled.stats)
+    }
+
+    fn reset_stats(&mut self) {
+        for scheduled in self.tasks.as_mut_slice() {
+            scheduled.stats = TaskStats::default();
+        }
+    }
+
+    fn find(&self, handle: TaskHandle) -> Result<usize, UnknownTask> {
+        self.tasks
+            .as_slice()
+            .iter()
+            .position(|scheduled| scheduled.handle == handle)
+            .ok_or(UnknownTask)
+    }
+
+    fn suspend(&mut self, handle: TaskHandle) -> Result<(), UnknownTask> {
+        let i = self.find(handle)?;
+        self.tasks.as_mut_slice()[i].is_suspended = true;
+        Ok(())
+    }
+
+    fn resume(&mut self, handle: TaskHandle) -> Result<(), UnknownTask> {
+        let i = self.find(handle)?;
+        self.tasks.as_mut_slice()[i].is_suspended = false;
+        Ok(())
+    }
+
+    fn reschedule(&mut self, handle: TaskHandle, at: Instant) -> Result<(), UnknownTask> {
+        let i = self.find(handle)?;
+        self.tasks.as_mut_slice()[i].at = SchedulePoint::At(at);
+        Ok(())
+    }
+
+    fn pick_task(&mut self, now: Instant) -> Option<&mut Scheduled<Tasks::Task>> {
         let task_i = self.pick_timed(now).or_else(|| self.pick_in_order());
 
-        task_i.map(|i| &mut self.tasks[i])
+        task_i.map(|i| &mut self.tasks.as_mut_slice()[i])
     }
 
+    /// Pick the due timed task of the highest priority, the earliest one among equals.
     fn pick_timed(&mut self, now: Instant) -> Option<usize> {
         self.tasks
-            .iter_mut()
+            .as_slice()
+            .iter()
             .enumerate()
-            .fold(None, |result, candidate| match result {
-                None => Some(candidate).filter(|(_, scheduled)| scheduled.is_timed()),
-                Some(result) => Some(candidate)
-                    .filter(|(_, scheduled)| scheduled.is_timed_before(result.1.at))
-                    .or(Some(result)),
-            })
-            .and_then(|(i, result)| {
-                if result.is_timed_no_later(SchedulePoint::At(now)) {
-                    Some(i)
-                } else {
-                    None
-                }
+            .filter(|(_, scheduled)| !scheduled.is_suspended)
+            .filter_map(|(i, scheduled)| match scheduled.at {
+                SchedulePoint::At(at) if at <= now => Some((i, scheduled.priority, at)),
+                _ => None,
             })
+            .min_by_key(|&(_, priority, at)| (Reverse(priority), at))
+            .map(|(i, _, _)| i)
     }
 
     fn pick_in_order(&mut self) -> Option<usize> {
-        let task_count = self.tasks.len();
-        let (tail, head) = self.tasks.split_at_mut(self.next_in_order);
+        let tasks = self.tasks.as_mut_slice();
+        let task_count = tasks.len();
+        if task_count == 0 {
+            return None;
+        }
+        let (tail, head) = tasks.split_at_mut(self.next_in_order);
 
         let next_in_order = head.iter_mut().chain(tail.iter_mut()).enumerate().fold(
             None,
             |result, (i, candidate)| {
-                if result.is_none() && !candidate.is_timed() {
+                if result.is_none() && candidate.is_in_order() {
                     Some((self.next_in_order + i) % task_count)
                 } else {
                     result
@@ -88,8 +320,65 @@ impl<State, Action> Scheduler<State, Action> {
     }
 }
 
+/// The storage of the scheduled tasks.
+trait TaskList {
+    type Task;
+
+    fn as_slice(&self) -> &[Scheduled<Self::Task>];
+    fn as_mut_slice(&mut self) -> &mut [Scheduled<Self::Task>];
+}
+
+#[cfg(feature = "alloc")]
+impl<Task> TaskList for Vec<Scheduled<Task>> {
+    type Task = Task;
+
+    fn as_slice(&self) -> &[Scheduled<Task>] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [Scheduled<Task>] {
+        self
+    }
+}
+
+impl<Task, const N: usize> TaskList for [Scheduled<Task>; N] {
+    type Task = Task;
+
+    fn as_slice(&self) -> &[Scheduled<Task>] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [Scheduled<Task>] {
+        self
+    }
+}
+
+/// Refers to a scheduled task, stays valid as the other tasks are added or removed.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct TaskHandle(u32);
+
+/// When the next task is due, as returned by `next_deadline()`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Deadline {
+    /// A task running in order is due, there's no time to sleep.
+    Now,
+    /// The earliest instant a timed task is due at, possibly already past.
+    At(Instant),
+    /// No task is left to run, e.g. all of them are suspended or the scheduler is empty.
+    Never,
+}
+
+/// The task the handle refers to has been removed.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct UnknownTask;
+
 struct Scheduled<Task> {
+    handle: TaskHandle,
     at: SchedulePoint,
+    is_suspended: bool,
+    priority: Priority,
+    subscriptions: Topics,
+    stats: TaskStats,
     task: Task,
 }
 
@@ -99,31 +388,362 @@ enum SchedulePoint {
     At(Instant),
 }
 
-impl SchedulePoint {
-    fn cmp_instant(&self, other: Self) -> Option<Ordering> {
-        use SchedulePoint::*;
+impl<Task> Scheduled<Task> {
+    fn new<State, Action, T>(handle: TaskHandle, task: Task) -> Self
+    where
+        Task: DerefMut<Target = T>,
+        T: MultiActionTask<State, Action> + ?Sized,
+    {
+        Self {
+            handle,
+            at: SchedulePoint::InOrder,
+            is_suspended: false,
+            priority: task.priority(),
+            subscriptions: task.subscriptions(),
+            stats: TaskStats::default(),
+            task,
+        }
+    }
+
+    fn is_in_order(&self) -> bool {
+        !self.is_suspended && matches!(self.at, SchedulePoint::InOrder)
+    }
+}
 
-        match (self, other) {
-            (InOrder, _) => None,
-            (_, InOrder) => None,
-            (At(a), At(b)) => Some(a.cmp(&b)),
+/// How timely a task is run.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct TaskStats {
+    pub run_count: u32,
+    /// The longest delay of a timed run past its deadline.
+    pub max_lateness: Duration,
+    /// The number of timed runs later than [`LATENESS_TOLERANCE_US`] past the deadline.
+    pub missed_deadlines: u32,
+}
+
+impl Default for TaskStats {
+    fn default() -> Self {
+        Self {
+            run_count: 0,
+            max_lateness: Duration::from_ticks(0),
+            missed_deadlines: 0,
         }
     }
 }
 
-impl<Task> Scheduled<Task> {
-    fn is_timed(&self) -> bool {
-        matches!(self.at, SchedulePoint::At(_))
+impl TaskStats {
+    fn record(&mut self, now: Instant, at: SchedulePoint) {
+        self.run_count = self.run_count.saturating_add(1);
+
+        if let SchedulePoint::At(at) = at {
+            let lateness = now
+                .checked_duration_since(at)
+                .unwrap_or(Duration::from_ticks(0));
+            self.max_lateness = self.max_lateness.max(lateness);
+            if lateness.ticks() > LATENESS_TOLERANCE_US {
+                self.missed_deadlines = self.missed_deadlines.saturating_add(1);
+            }
+        }
+    }
+}
+
+pub const LATENESS_TOLERANCE_US: u64 = 100;
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::Duration,
+        task::{InlineFnTask, Task},
+    };
+
+    #[cfg(feature = "alloc")]
+    use crate::task::FnTask;
+
+    use super::*;
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn next_deadline_test() {
+        let mut scheduler = Scheduler::<(), ()>::new([
+            Box::new(FnTask::new(|_: &mut ()| {
+                (None, NextRun::After(Duration::from_ticks(300)))
+            })) as _,
+            Box::new(FnTask::new(|_: &mut ()| {
+                (None, NextRun::After(Duration::from_ticks(200)))
+            })) as _,
+        ]);
+
+        assert_eq!(scheduler.next_deadline(), Deadline::Now);
+
+        let now = Instant::from_ticks(1_000);
+        scheduler.run(now, &mut (), &mut |_| {});
+        assert_eq!(
+            scheduler.next_deadline(),
+            Deadline::Now,
+            "a task is still due in order"
+        );
+
+        scheduler.run(now, &mut (), &mut |_| {});
+        assert_eq!(
+            scheduler.next_deadline(),
+            Deadline::At(Instant::from_ticks(1_200))
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn priority_test() {
+        let mut scheduler = Scheduler::<Vec<&str>, ()>::new([
+            Box::new(FnTask::new(|log: &mut Vec<&str>| {
+                log.push("normal");
+                (None, NextRun::After(Duration::from_ticks(100)))
+            })) as _,
+            Box::new(HighPriority) as _,
+        ]);
+        let mut log = Vec::new();
+
+        let now = Instant::from_ticks(0);
+        scheduler.run(now, &mut log, &mut |_| {});
+        scheduler.run(now, &mut log, &mut |_| {});
+        assert_eq!(log, ["normal", "high"]);
+
+        // Both are due, the normal priority one since earlier.
+        log.clear();
+        scheduler.run(Instant::from_ticks(200), &mut log, &mut |_| {});
+        scheduler.run(Instant::from_ticks(200), &mut log, &mut |_| {});
+        assert_eq!(log, ["high", "normal"]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn stats_test() {
+        let mut scheduler = Scheduler::<Vec<&str>, ()>::new([Box::new(HighPriority) as _]);
+        let mut log = Vec::new();
+
+        scheduler.run(Instant::from_ticks(0), &mut log, &mut |_| {});
+        scheduler.run(Instant::from_ticks(100), &mut log, &mut |_| {});
+        scheduler.run(Instant::from_ticks(400), &mut log, &mut |_| {});
+        // Not due yet.
+        scheduler.run(Instant::from_ticks(450), &mut log, &mut |_| {});
+
+        assert_eq!(
+            scheduler.stats().collect::<Vec<_>>(),
+            [&TaskStats {
+                run_count: 3,
+                max_lateness: Duration::from_ticks(200),
+                missed_deadlines: 1,
+            }]
+        );
+
+        scheduler.reset_stats();
+        assert_eq!(scheduler.stats().next().unwrap().run_count, 0);
+    }
+
+    struct HighPriority;
+
+    impl Task<Vec<&'static str>, ()> for HighPriority {
+        fn run(&mut self, log: &mut Vec<&'static str>) -> (Option<()>, NextRun) {
+            log.push("high");
+            (None, NextRun::After(Duration::from_ticks(100)))
+        }
+
+        fn priority(&self) -> Priority {
+            Priority::High
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn multi_action_test() {
+        struct ChargeComplete;
+
+        impl MultiActionTask<(), &'static str> for ChargeComplete {
+            fn run(&mut self, _: &mut (), actions: &mut dyn ActionSink<&'static str>) -> NextRun {
+                actions.push("stop charging");
+                actions.push("beep");
+                NextRun::InOrder
+            }
+        }
+
+        let mut scheduler = Scheduler::new([
+            Box::new(ChargeComplete) as Box<dyn MultiActionTask<_, _>>,
+            Box::new(FnTask::new(|_: &mut ()| (Some("tick"), NextRun::InOrder))) as _,
+        ]);
+        let mut actions = Vec::new();
+
+        scheduler.run(Instant::from_ticks(0), &mut (), &mut |action| {
+            actions.push(action)
+        });
+        scheduler.run(Instant::from_ticks(0), &mut (), &mut |action| {
+            actions.push(action)
+        });
+
+        assert_eq!(actions, ["stop charging", "beep", "tick"]);
+    }
+
+    #[test]
+    fn static_scheduler_test() {
+        let mut timed = InlineFnTask::new(|log: &mut Vec<&str>| {
+            log.push("timed");
+            (None::<()>, NextRun::After(Duration::from_ticks(100)))
+        });
+        let mut in_order = InlineFnTask::new(|log: &mut Vec<&str>| {
+            log.push("in order");
+            (None, NextRun::InOrder)
+        });
+        let mut high = HighPriority;
+        let mut scheduler = StaticScheduler::new([&mut timed as _, &mut in_order as _, &mut high]);
+        let mut log = Vec::new();
+
+        for _ in 0..4 {
+            scheduler.run(Instant::from_ticks(0), &mut log, &mut |_| {});
+        }
+        assert_eq!(log, ["timed", "in order", "high", "in order"]);
+        assert_eq!(scheduler.next_deadline(), Deadline::Now);
+
+        log.clear();
+        scheduler.run(Instant::from_ticks(100), &mut log, &mut |_| {});
+        scheduler.run(Instant::from_ticks(100), &mut log, &mut |_| {});
+        assert_eq!(log, ["high", "timed"]);
+        assert_eq!(scheduler.stats().map(|stats| stats.run_count), [2, 2, 2]);
     }
 
-    fn is_timed_before(&self, pt: SchedulePoint) -> bool {
-        matches!(self.at.cmp_instant(pt), Some(Ordering::Less))
+    #[cfg(feature = "alloc")]
+    fn logging(
+        name: &'static str,
+        next_run: NextRun,
+    ) -> Box<dyn MultiActionTask<Vec<&'static str>, ()>> {
+        Box::new(FnTask::new(move |log: &mut Vec<&str>| {
+            log.push(name);
+            (None, next_run)
+        }))
     }
 
-    fn is_timed_no_later(&self, pt: SchedulePoint) -> bool {
-        matches!(
-            self.at.cmp_instant(pt),
-            Some(Ordering::Less) | Some(Ordering::Equal)
-        )
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn remove_test() {
+        let mut scheduler = Scheduler::new([
+            logging("a", NextRun::InOrder),
+            logging("b", NextRun::InOrder),
+            logging("c", NextRun::InOrder),
+        ]);
+        let [a, _, c] = scheduler.handles().collect::<Vec<_>>()[..] else {
+            panic!("Unexpected task count");
+        };
+        let mut log = Vec::new();
+        let now = Instant::from_ticks(0);
+
+        scheduler.run(now, &mut log, &mut |_| {});
+        // The cursor stays on "b", now moved in place of "a".
+        assert!(scheduler.remove(a).is_ok());
+        scheduler.run(now, &mut log, &mut |_| {});
+        // The cursor moves from the removed "c" on to the following task, wrapping around.
+        assert!(scheduler.remove(c).is_ok());
+        scheduler.run(now, &mut log, &mut |_| {});
+        assert_eq!(log, ["a", "b", "b"]);
+
+        assert!(scheduler.remove(a).is_err());
+        assert_eq!(scheduler.suspend(c), Err(UnknownTask));
+
+        let d = scheduler.add(logging("d", NextRun::InOrder));
+        assert_ne!(d, a);
+        log.clear();
+        scheduler.run(now, &mut log, &mut |_| {});
+        scheduler.run(now, &mut log, &mut |_| {});
+        assert_eq!(log, ["b", "d"]);
+
+        let [b, d] = scheduler.handles().collect::<Vec<_>>()[..] else {
+            panic!("Unexpected task count");
+        };
+        assert!(scheduler.remove(b).is_ok());
+        assert!(scheduler.remove(d).is_ok());
+        scheduler.run(now, &mut log, &mut |_| {});
+        assert_eq!(scheduler.next_deadline(), Deadline::Never);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn suspend_test() {
+        let mut scheduler = Scheduler::new([
+            logging("timed", NextRun::After(Duration::from_ticks(100))),
+            logging("other", NextRun::After(Duration::from_ticks(1_000))),
+        ]);
+        let [timed, other] = scheduler.handles().collect::<Vec<_>>()[..] else {
+            panic!("Unexpected task count");
+        };
+        let mut log = Vec::new();
+
+        scheduler.run(Instant::from_ticks(0), &mut log, &mut |_| {});
+        scheduler.run(Instant::from_ticks(0), &mut log, &mut |_| {});
+        assert_eq!(
+            scheduler.next_deadline(),
+            Deadline::At(Instant::from_ticks(100))
+        );
+
+        scheduler.suspend(timed).unwrap();
+        assert_eq!(
+            scheduler.next_deadline(),
+            Deadline::At(Instant::from_ticks(1_000))
+        );
+        scheduler.run(Instant::from_ticks(200), &mut log, &mut |_| {});
+        assert_eq!(log, ["timed", "other"]);
+
+        // Overdue, so run right away.
+        scheduler.resume(timed).unwrap();
+        scheduler.run(Instant::from_ticks(300), &mut log, &mut |_| {});
+        assert_eq!(log, ["timed", "other", "timed"]);
+
+        scheduler
+            .reschedule(other, Instant::from_ticks(350))
+            .unwrap();
+        assert_eq!(
+            scheduler.next_deadline(),
+            Deadline::At(Instant::from_ticks(350))
+        );
+        scheduler.run(Instant::from_ticks(350), &mut log, &mut |_| {});
+        assert_eq!(log, ["timed", "other", "timed", "other"]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn wake_test() {
+        struct Subscribed;
+
+        impl Task<Vec<&'static str>, ()> for Subscribed {
+            fn run(&mut self, log: &mut Vec<&'static str>) -> (Option<()>, NextRun) {
+                log.push("subscribed");
+                (None, NextRun::After(Duration::from_ticks(1_000)))
+            }
+
+            fn subscriptions(&self) -> Topics {
+                Topics::topic(1)
+            }
+        }
+
+        let mut scheduler = Scheduler::new([
+            Box::new(Subscribed) as _,
+            logging("other", NextRun::After(Duration::from_ticks(1_000))),
+        ]);
+        let mut log = Vec::new();
+
+        scheduler.run(Instant::from_ticks(0), &mut log, &mut |_| {});
+        scheduler.run(Instant::from_ticks(0), &mut log, &mut |_| {});
+
+        scheduler.wake(Instant::from_ticks(100), Topics::topic(0));
+        assert_eq!(
+            scheduler.next_deadline(),
+            Deadline::At(Instant::from_ticks(1_000))
+        );
+
+        scheduler.wake(
+            Instant::from_ticks(100),
+            Topics::topic(0) | Topics::topic(1),
+        );
+        assert_eq!(
+            scheduler.next_deadline(),
+            Deadline::At(Instant::from_ticks(100))
+        );
+        scheduler.run(Instant::from_ticks(100), &mut log, &mut |_| {});
+        scheduler.run(Instant::from_ticks(100), &mut log, &mut |_| {});
+        assert_eq!(log, ["subscribed", "other", "subscribed"]);
     }
 }
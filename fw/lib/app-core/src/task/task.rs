@@ -1,11 +1,97 @@
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+use core::{marker::PhantomData, ops::BitOr};
+
+#[cfg(feature = "alloc")]
 use alloc::boxed::Box;
 
 use crate::common::Duration;
 
+use super::ActionSink;
+
 pub trait Task<State, Action> {
     fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun);
+
+    /// The priority among the timed tasks due at the same time.
+    fn priority(&self) -> Priority {
+        Priority::Normal
+    }
+
+    /// The topics of the events waking the task up before its next timed run.
+    fn subscriptions(&self) -> Topics {
+        Topics::NONE
+    }
+}
+
+/// A task emitting any number of actions per run, unlike [`Task`] emitting one at most.
+///
+/// Every [`Task`] is also a [`MultiActionTask`].
+pub trait MultiActionTask<State, Action> {
+    fn run(&mut self, state: &mut State, actions: &mut dyn ActionSink<Action>) -> NextRun;
+
+    /// The priority among the timed tasks due at the same time.
+    fn priority(&self) -> Priority {
+        Priority::Normal
+    }
+
+    /// The topics of the events waking the task up before its next timed run.
+    fn subscriptions(&self) -> Topics {
+        Topics::NONE
+    }
+}
+
+impl<State, Action, T> MultiActionTask<State, Action> for T
+where
+    T: Task<State, Action> + ?Sized,
+{
+    fn run(&mut self, state: &mut State, actions: &mut dyn ActionSink<Action>) -> NextRun {
+        let (action, next_run) = Task::run(self, state);
+        if let Some(action) = action {
+            actions.push(action);
+        }
+        next_run
+    }
+
+    fn priority(&self) -> Priority {
+        Task::priority(self)
+    }
+
+    fn subscriptions(&self) -> Topics {
+        Task::subscriptions(self)
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+/// A set of event topics, the events themselves are up to the app.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct Topics(u32);
+
+impl Topics {
+    pub const NONE: Self = Self(0);
+
+    /// The set of the single topic `n`, `0..32`.
+    pub const fn topic(n: u32) -> Self {
+        Self(1 << n)
+    }
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Topics {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
 }
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
@@ -14,29 +100,117 @@ pub enum NextRun {
     After(Duration),
 }
 
+/// A task running a boxed closure.
+///
+/// A closure wrapping another task should carry over its priority and subscriptions,
+/// see [`Self::with_priority()`] and [`Self::with_subscriptions()`].
+#[cfg(feature = "alloc")]
 pub struct FnTask<State, Action> {
     f: Box<dyn FnMut(&mut State) -> (Option<Action>, NextRun)>,
+    priority: Priority,
+    subscriptions: Topics,
 }
 
+#[cfg(feature = "alloc")]
 impl<State, Action> FnTask<State, Action> {
     pub fn new<F>(f: F) -> Self
     where
         F: FnMut(&mut State) -> (Option<Action>, NextRun) + 'static,
     {
-        Self { f: Box::new(f) }
+        Self {
+            f: Box::new(f),
+            priority: Priority::Normal,
+            subscriptions: Topics::NONE,
+        }
+    }
+
+    pub fn with_priority(self, priority: Priority) -> Self {
+        Self { priority, ..self }
+    }
+
+    pub fn with_subscriptions(self, subscriptions: Topics) -> Self {
+        Self {
+            subscriptions,
+            ..self
+        }
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<State, Action> Task<State, Action> for FnTask<State, Action> {
     fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
         (self.f)(state)
     }
+
+    fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    fn subscriptions(&self) -> Topics {
+        self.subscriptions
+    }
+}
+
+/// A task running a closure stored in place, unlike the boxed one of `FnTask`.
+///
+/// A closure wrapping another task should carry over its priority and subscriptions,
+/// see [`Self::with_priority()`] and [`Self::with_subscriptions()`].
+pub struct InlineFnTask<State, Action, F> {
+    f: F,
+    priority: Priority,
+    subscriptions: Topics,
+    _marker: PhantomData<fn(&mut State) -> Option<Action>>,
+}
+
+impl<State, Action, F> InlineFnTask<State, Action, F>
+where
+    F: FnMut(&mut State) -> (Option<Action>, NextRun),
+{
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            priority: Priority::Normal,
+            subscriptions: Topics::NONE,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with_priority(self, priority: Priority) -> Self {
+        Self { priority, ..self }
+    }
+
+    pub fn with_subscriptions(self, subscriptions: Topics) -> Self {
+        Self {
+            subscriptions,
+            ..self
+        }
+    }
+}
+
+impl<State, Action, F> Task<State, Action> for InlineFnTask<State, Action, F>
+where
+    F: FnMut(&mut State) -> (Option<Action>, NextRun),
+{
+    fn run(&mut self, state: &mut State) -> (Option<Action>, NextRun) {
+        (self.f)(state)
+    }
+
+    fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    fn subscriptions(&self) -> Topics {
+        self.subscriptions
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    #[cfg(feature = "alloc")]
+    use super::FnTask;
+    use super::{InlineFnTask, NextRun, Task};
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn fn_task_test() {
         let mut state = 0;
@@ -50,4 +224,18 @@ mod tests {
         assert_eq!(state, 1);
         assert_eq!(action, (Some(42), NextRun::InOrder));
     }
+
+    #[test]
+    fn inline_fn_task_test() {
+        let mut state = 0;
+        let mut task = InlineFnTask::new(|state: &mut i32| {
+            *state += 1;
+            (None::<i32>, NextRun::InOrder)
+        });
+
+        task.run(&mut state);
+        task.run(&mut state);
+
+        assert_eq!(state, 2);
+    }
 }
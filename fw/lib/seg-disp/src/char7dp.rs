@@ -1,5 +1,9 @@
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+
 use snafu::Snafu;
 
 /// Individual segments of a seven-segment indicator with a decimal point.
@@ -32,6 +36,19 @@ pub enum Segment7DP {
     DP = 0b10000000,
 }
 
+impl Segment7DP {
+    pub const ALL: [Segment7DP; 8] = [
+        Segment7DP::A,
+        Segment7DP::B,
+        Segment7DP::C,
+        Segment7DP::D,
+        Segment7DP::E,
+        Segment7DP::F,
+        Segment7DP::G,
+        Segment7DP::DP,
+    ];
+}
+
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 #[repr(transparent)]
 pub struct Char7DP {
@@ -138,7 +155,7 @@ impl Char7DP {
         self
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "alloc"))]
     pub fn try_from_str(value: &str) -> Result<Vec<Char7DP>, Char7DPTryFromError> {
         let mut s = Vec::with_capacity(value.len());
 
@@ -159,7 +176,7 @@ impl Char7DP {
         Ok(s)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "alloc"))]
     pub fn render(value: &[Self]) -> String {
         use Segment7DP::*;
 
@@ -1,26 +1,79 @@
 use core::slice::SliceIndex;
 
-use crate::char7dp::Char7DP;
+use crate::char7dp::{Char7DP, Segment7DP};
 
 type Duration = fugit::Duration<u64, 1, 1_000_000>;
 
+/// The way the display is multiplexed.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum ScanMode {
+    /// Light one digit at a time, the current depends on the number of lit segments.
+    #[default]
+    Digit,
+    /// Light one segment at a time across all the digits, the current per segment stays constant.
+    ///
+    /// The segment compensation and the digit gain are not applicable in this mode.
+    Segment,
+}
+
 pub struct Disp<const N: usize> {
     chars: [Char7DP; N],
+    scan_mode: ScanMode,
     update_period: Duration,
     duty_cycle: f32,
-    state: State<N>,
+    segment_compensation: f32,
+    digit_gain: [f32; N],
+    /// The frame the current step belongs to, latched at the beginning of the step.
+    latched: Frame<N>,
+    state: State,
 }
 
 impl<const N: usize> Disp<N> {
     pub fn new(update_period: Duration, duty_cycle: f32) -> Self {
         Self {
             chars: [Default::default(); N],
+            scan_mode: ScanMode::default(),
             update_period,
             duty_cycle,
+            segment_compensation: 0.0,
+            digit_gain: [1.0; N],
+            latched: Frame {
+                chars: [Default::default(); N],
+                scan_mode: ScanMode::default(),
+                update_period,
+                on_ratios: [duty_cycle; N],
+            },
             state: State::default(),
         }
     }
 
+    /// Switch the multiplexing, the frame period (`N` update periods) stays the same.
+    pub fn set_scan_mode(&mut self, scan_mode: ScanMode) {
+        if scan_mode != self.scan_mode {
+            self.scan_mode = scan_mode;
+            self.state = State::default();
+        }
+    }
+
+    /// The share of the update period a digit is lit for, `0.0..=1.0`.
+    pub fn set_duty_cycle(&mut self, duty_cycle: f32) {
+        self.duty_cycle = duty_cycle.clamp(0.0, 1.0);
+    }
+
+    /// Scale the on-time of every digit by the share of its lit segments, `0.0..=1.0`.
+    ///
+    /// The segments of a digit share the common current, so a digit with many lit segments
+    /// looks dimmer than a lone "1". At `1.0` the on-time is proportional to the number of
+    /// lit segments, at `0.0` (the default) the compensation is off.
+    pub fn set_segment_compensation(&mut self, segment_compensation: f32) {
+        self.segment_compensation = segment_compensation.clamp(0.0, 1.0);
+    }
+
+    /// Scale the on-time of the individual digits to compensate for the differences between indicators.
+    pub fn set_digit_gain(&mut self, digit_gain: [f32; N]) {
+        self.digit_gain = digit_gain;
+    }
+
     pub fn set_chars(&mut self, chars: [Char7DP; N]) {
         self.chars = chars;
     }
@@ -32,64 +85,246 @@ impl<const N: usize> Disp<N> {
         self.chars[pos].copy_from_slice(chars);
     }
 
+    /// The complete frame as currently configured, for the drivers refreshing the display on their own.
+    pub fn frame(&self) -> Frame<N> {
+        let mut on_ratios = [self.duty_cycle; N];
+        if self.scan_mode == ScanMode::Digit {
+            for (index, on_ratio) in on_ratios.iter_mut().enumerate() {
+                *on_ratio = self.on_ratio(index);
+            }
+        }
+
+        Frame {
+            chars: self.chars,
+            scan_mode: self.scan_mode,
+            update_period: self.update_period,
+            on_ratios,
+        }
+    }
+
     pub fn run(&mut self) -> (Action, Duration) {
-        let (action, delay) = if self.state.is_char_active {
-            let c = self.chars[self.state.char_index];
-            (
-                Action::Render(c, self.state.char_index),
-                self.delay(self.duty_cycle),
-            )
-        } else {
-            (
-                Action::Render(Char7DP::space(), self.state.char_index),
-                self.delay(1.0 - self.duty_cycle),
-            )
-        };
+        if self.state.is_active {
+            // Fixed for the whole step, even if the chars change in the meantime.
+            self.latched = self.frame();
+        }
 
-        self.state = self.state.next();
+        let step = self.latched.step(self.state.index, self.state.is_active);
+        self.state = self.state.next(self.latched.step_count());
 
-        (action, delay)
+        step
     }
 
-    fn delay(&self, k: f32) -> Duration {
-        Duration::from_ticks((self.update_period.ticks() as f32 * k) as u64)
+    fn on_ratio(&self, index: usize) -> f32 {
+        let lit_ratio = self.chars[index].state().count_ones() as f32 / 8.0;
+        let segment_gain = 1.0 - self.segment_compensation * (1.0 - lit_ratio);
+
+        (self.duty_cycle * segment_gain * self.digit_gain[index]).clamp(0.0, 1.0)
     }
 }
 
-struct State<const N: usize> {
-    char_index: usize,
-    is_char_active: bool,
+/// Everything a display frame depends on, see [`Disp::frame()`].
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Frame<const N: usize> {
+    chars: [Char7DP; N],
+    scan_mode: ScanMode,
+    update_period: Duration,
+    /// The share of the step each digit is lit for, all the same in [`ScanMode::Segment`].
+    on_ratios: [f32; N],
 }
 
-impl<const N: usize> Default for State<N> {
+impl<const N: usize> Frame<N> {
+    /// The chars, the digit 0 first.
+    pub fn chars(&self) -> &[Char7DP; N] {
+        &self.chars
+    }
+
+    /// The on and off steps making up the frame, in order.
+    pub fn steps(&self) -> impl Iterator<Item = (Action, Duration)> + '_ {
+        (0..self.step_count())
+            .flat_map(move |index| [self.step(index, true), self.step(index, false)])
+    }
+
+    fn step_count(&self) -> usize {
+        match self.scan_mode {
+            ScanMode::Digit => N,
+            ScanMode::Segment => Segment7DP::ALL.len(),
+        }
+    }
+
+    fn step(&self, index: usize, is_active: bool) -> (Action, Duration) {
+        match self.scan_mode {
+            ScanMode::Digit => {
+                let on_ratio = self.on_ratios[index];
+                if is_active {
+                    (
+                        Action::Render(self.chars[index], index),
+                        scale(self.update_period, on_ratio),
+                    )
+                } else {
+                    (
+                        Action::Render(Char7DP::space(), index),
+                        scale(self.update_period, 1.0 - on_ratio),
+                    )
+                }
+            }
+            ScanMode::Segment => {
+                let segment = Segment7DP::ALL[index];
+                // A frame takes 8 segment steps instead of `N` digit steps.
+                let step_period = self.update_period * N as u32 / Segment7DP::ALL.len() as u32;
+                let on_ratio = self.on_ratios.first().copied().unwrap_or_default();
+                if is_active {
+                    let digits = self
+                        .chars
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, c)| c.state() & segment as u8 != 0)
+                        .fold(0, |digits, (i, _)| digits | 1 << i);
+                    (
+                        Action::RenderSegment(segment, digits),
+                        scale(step_period, on_ratio),
+                    )
+                } else {
+                    (
+                        Action::RenderSegment(segment, 0),
+                        scale(step_period, 1.0 - on_ratio),
+                    )
+                }
+            }
+        }
+    }
+}
+
+fn scale(period: Duration, k: f32) -> Duration {
+    Duration::from_ticks((period.ticks() as f32 * k) as u64)
+}
+
+struct State {
+    /// The index of the digit or the segment being lit.
+    index: usize,
+    /// Whether the digit or the segment is lit (as opposed to the rest of the step).
+    is_active: bool,
+}
+
+impl Default for State {
     fn default() -> Self {
         Self {
-            char_index: 0,
-            is_char_active: true,
+            index: 0,
+            is_active: true,
         }
     }
 }
 
-impl<const N: usize> State<N> {
-    fn next(&self) -> Self {
-        if self.is_char_active {
+impl State {
+    fn next(&self, count: usize) -> Self {
+        if self.is_active {
             Self {
-                char_index: self.char_index,
-                is_char_active: false,
+                index: self.index,
+                is_active: false,
             }
         } else {
             Self {
-                char_index: self.next_char_index(),
-                is_char_active: true,
+                index: (self.index + 1) % count,
+                is_active: true,
             }
         }
     }
-
-    fn next_char_index(&self) -> usize {
-        (self.char_index + 1) % N
-    }
 }
 
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum Action {
+    /// Light the char on the digit with the given index, turning the other digits off.
     Render(Char7DP, usize),
+    /// Light the segment on the digits in the mask (bit 0 is the digit 0), turning the other segments off.
+    RenderSegment(Segment7DP, u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duty_cycle_test() {
+        let mut disp = Disp::<2>::new(Duration::from_ticks(2_000), 1.0);
+        disp.set_chars([
+            Char7DP::try_from_u8(1).unwrap(),
+            Char7DP::try_from_u8(2).unwrap(),
+        ]);
+        disp.set_duty_cycle(0.25);
+
+        let steps = (0..4)
+            .map(|_| match disp.run() {
+                (Action::Render(c, i), delay) => (c, i, delay.ticks()),
+                _ => panic!("Unexpected scan mode"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            steps,
+            [
+                (Char7DP::try_from_u8(1).unwrap(), 0, 500),
+                (Char7DP::space(), 0, 1_500),
+                (Char7DP::try_from_u8(2).unwrap(), 1, 500),
+                (Char7DP::space(), 1, 1_500),
+            ]
+        );
+    }
+
+    #[test]
+    fn brightness_compensation_test() {
+        let mut disp = Disp::<3>::new(Duration::from_ticks(2_000), 1.0);
+        disp.set_chars([
+            Char7DP::try_from_u8(8).unwrap().with_dp(),
+            Char7DP::try_from_u8(1).unwrap(),
+            Char7DP::try_from_u8(8).unwrap().with_dp(),
+        ]);
+        disp.set_segment_compensation(1.0);
+        disp.set_digit_gain([1.0, 1.0, 0.5]);
+
+        let delays = (0..6).map(|_| disp.run().1.ticks()).collect::<Vec<_>>();
+
+        assert_eq!(delays, [2_000, 0, 500, 1_500, 1_000, 1_000]);
+    }
+
+    #[test]
+    fn segment_scan_test() {
+        let mut disp = Disp::<2>::new(Duration::from_ticks(2_000), 1.0);
+        disp.set_chars([
+            Char7DP::try_from_u8(1).unwrap(),
+            Char7DP::try_from_u8(7).unwrap(),
+        ]);
+        disp.set_scan_mode(ScanMode::Segment);
+        disp.set_duty_cycle(0.5);
+
+        let steps = (0..16)
+            .map(|_| match disp.run() {
+                (Action::RenderSegment(segment, digits), delay) => (segment, digits, delay.ticks()),
+                _ => panic!("Unexpected scan mode"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(steps[0], (Segment7DP::A, 0b10, 250));
+        assert_eq!(steps[1], (Segment7DP::A, 0b00, 250));
+        assert_eq!(steps[2], (Segment7DP::B, 0b11, 250));
+        assert_eq!(steps[4], (Segment7DP::C, 0b11, 250));
+        assert_eq!(steps[6], (Segment7DP::D, 0b00, 250));
+        // The frame period is the same as in the digit mode.
+        assert_eq!(steps.iter().map(|&(_, _, delay)| delay).sum::<u64>(), 4_000);
+    }
+
+    #[test]
+    fn frame_test() {
+        let mut disp = Disp::<2>::new(Duration::from_ticks(2_000), 0.5);
+        disp.set_chars([
+            Char7DP::try_from_u8(1).unwrap(),
+            Char7DP::try_from_u8(2).unwrap(),
+        ]);
+
+        let frame = disp.frame();
+        let steps = (0..4).map(|_| disp.run()).collect::<Vec<_>>();
+        assert_eq!(frame.steps().collect::<Vec<_>>(), steps);
+
+        disp.set_scan_mode(ScanMode::Segment);
+        assert_ne!(disp.frame(), frame);
+        assert_eq!(disp.frame().steps().count(), 16);
+    }
 }
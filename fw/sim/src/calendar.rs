@@ -0,0 +1,58 @@
+use app_core::{calendar::days_in_month, common::Duration, state::RTC};
+
+/// Turns the virtual time into the RTC date and time, starting at a fixed date.
+#[derive(Clone, Copy)]
+pub struct Calendar {
+    year: u16,
+    month: u8,
+    day: u8,
+    /// 0..=6, 0 is Sunday.
+    day_of_week: u8,
+    /// The time of day to start at, in seconds.
+    start_second: u64,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        // The same as the firmware, when there are no saved settings.
+        Self {
+            year: 2023,
+            month: 4,
+            day: 7,
+            day_of_week: 5,
+            start_second: 9 * 3_600 + 60,
+        }
+    }
+}
+
+impl Calendar {
+    pub fn rtc(&self, elapsed: Duration) -> RTC {
+        let seconds = self.start_second + elapsed.to_secs();
+        let (days, second_of_day) = (seconds / SECONDS_PER_DAY, seconds % SECONDS_PER_DAY);
+
+        let (mut year, mut month, mut day) = (self.year, self.month, self.day);
+        for _ in 0..days {
+            day += 1;
+            if day > days_in_month(year, month) {
+                day = 1;
+                month += 1;
+                if month > 12 {
+                    month = 1;
+                    year += 1;
+                }
+            }
+        }
+
+        RTC {
+            year,
+            month,
+            day,
+            day_of_week: ((self.day_of_week as u64 + days) % 7) as u8,
+            hour: (second_of_day / 3_600) as u8,
+            minute: (second_of_day / 60 % 60) as u8,
+            second: (second_of_day % 60) as u8,
+        }
+    }
+}
+
+const SECONDS_PER_DAY: u64 = 24 * 3_600;
@@ -0,0 +1,270 @@
+//! Runs the app on the host, against a virtual clock and a simulated battery.
+//!
+//! The display is rendered in the terminal along with the battery and the charger status.
+
+use std::{
+    cell::{Cell, RefCell},
+    process::ExitCode,
+    rc::Rc,
+    time,
+};
+
+use app_core::{
+    action::Action,
+    battery_model::{self, Battery, CellParams},
+    common::{Duration, Instant},
+    features::{
+        charger::{Charger, ChargerAction},
+        date_view::DateView,
+        display::{Display, Refresh, DIGIT_COUNT},
+        power::PowerSaver,
+    },
+    state::State,
+    task::{
+        scheduler::{Deadline, Scheduler},
+        FnTask, NextRun, Task,
+    },
+};
+use seg_disp::{
+    char7dp::Char7DP,
+    disp::{Frame, ScanMode},
+};
+
+use crate::calendar::Calendar;
+
+mod calendar;
+
+const USAGE: &str = "\
+Usage: sim [OPTIONS]
+
+Options:
+  --speed <FACTOR>     Virtual seconds per real second, 0 to run as fast as possible [default: 60]
+  --hours <HOURS>      Virtual hours to run for [default: 24]
+  --soc <SOC>          Initial battery state of charge, 0..1 [default: 0.5]
+  --unplug-at <HOURS>  Disconnect the external power at the virtual hour
+  --plug-at <HOURS>    Connect the external power at the virtual hour
+  -h, --help           Print this help";
+
+struct Args {
+    speed: f64,
+    hours: f64,
+    soc: f32,
+    unplug_at: Option<f64>,
+    plug_at: Option<f64>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args {
+            speed: 60.0,
+            hours: 24.0,
+            soc: 0.5,
+            unplug_at: None,
+            plug_at: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || -> Result<f64, String> {
+                let value = args.next().ok_or(format!("{arg} needs a value"))?;
+                value
+                    .parse()
+                    .map_err(|_| format!("{arg}: not a number: {value}"))
+            };
+            match arg.as_str() {
+                "--speed" => parsed.speed = value()?,
+                "--hours" => parsed.hours = value()?,
+                "--soc" => parsed.soc = value()? as f32,
+                "--unplug-at" => parsed.unplug_at = Some(value()?),
+                "--plug-at" => parsed.plug_at = Some(value()?),
+                _ => return Err(format!("Unexpected argument: {arg}")),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Whether the external power is connected at the virtual hour.
+    fn ext_power(&self, hour: f64) -> bool {
+        let last_change = [(self.unplug_at, false), (self.plug_at, true)]
+            .into_iter()
+            .filter_map(|(at, ext_power)| at.filter(|&at| at <= hour).map(|at| (at, ext_power)))
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        // Connected from the start.
+        !matches!(last_change, Some((_, false)))
+    }
+}
+
+fn main() -> ExitCode {
+    if std::env::args().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let now = Rc::new(Cell::new(Instant::from_ticks(0)));
+    // The second cell slightly smaller, as they never quite match.
+    let cells = [
+        CellParams::default(),
+        CellParams {
+            capacity_ah: CellParams::default().capacity_ah * 0.95,
+            ..Default::default()
+        },
+    ];
+    let battery = Rc::new(RefCell::new(Battery::new(
+        cells.map(|params| battery_model::Cell::new(params, args.soc)),
+    )));
+    let ext_power = Rc::new(Cell::new(true));
+    let is_charging = Rc::new(Cell::new(false));
+    let calendar = Calendar::default();
+
+    let mut scheduler = Scheduler::<State, Action>::new([
+        Box::new(FnTask::new({
+            let now = now.clone();
+            move |state: &mut State| {
+                state.rtc = calendar.rtc(now.get().duration_since_epoch());
+                (None, NextRun::After(Duration::from_ticks(200_000)))
+            }
+        })) as _,
+        Box::new(Display::new(Refresh::Streamed, ScanMode::default())) as _,
+        Box::new({
+            let battery = battery.clone();
+            let ext_power = ext_power.clone();
+            let is_charging = is_charging.clone();
+            let mut charger = Charger::default();
+            let (priority, subscriptions) = (charger.priority(), charger.subscriptions());
+            FnTask::new(move |state: &mut State| {
+                state.bat_voltage = battery
+                    .borrow()
+                    .bat_voltage(current_a(ext_power.get(), is_charging.get()));
+                state.bat_temperature = Some({
+                    let [cell1, cell2] = &battery.borrow().cells;
+                    cell1.temperature_c().max(cell2.temperature_c())
+                });
+                charger.run(state)
+            })
+            .with_priority(priority)
+            .with_subscriptions(subscriptions)
+        }) as _,
+        Box::new(DateView::default()) as _,
+        Box::new(PowerSaver::default()) as _,
+    ]);
+    let mut state = State::default();
+
+    let end = Instant::from_ticks(0) + Duration::from_ticks((args.hours * 3.6e9) as u64);
+    let started_at = time::Instant::now();
+    let mut frame = None;
+
+    while now.get() < end {
+        ext_power.set(args.ext_power(hours(now.get())));
+        state.set_ext_power(ext_power.get());
+
+        state.now = now.get();
+        let mut is_changed = false;
+        scheduler.run(now.get(), &mut state, &mut |action| match action {
+            Action::DisplayFrame(new_frame) => {
+                frame = Some(new_frame);
+                is_changed |= args.speed > 0.0;
+            }
+            Action::Battery(action) => {
+                is_charging.set(matches!(action, ChargerAction::Charge));
+                is_changed = true;
+            }
+            _ => {}
+        });
+        scheduler.wake(now.get(), state.events.take_published());
+
+        if is_changed {
+            let status = status(&state, &battery.borrow(), is_charging.get());
+            draw(frame.as_ref(), &status, args.speed > 0.0);
+        }
+
+        let next = match scheduler.next_deadline() {
+            Deadline::Now => now.get(),
+            Deadline::At(deadline) => deadline.max(now.get()),
+            // Nothing left to run, skip to the end.
+            Deadline::Never => end,
+        };
+        battery.borrow_mut().step(
+            current_a(ext_power.get(), is_charging.get()),
+            next - now.get(),
+        );
+        now.set(next);
+
+        if args.speed > 0.0 {
+            let real = time::Duration::from_micros((next.ticks() as f64 / args.speed) as u64);
+            if let Some(delay) = real.checked_sub(started_at.elapsed()) {
+                std::thread::sleep(delay);
+            }
+        }
+    }
+
+    let status = status(&state, &battery.borrow(), is_charging.get());
+    draw(frame.as_ref(), &status, false);
+
+    ExitCode::SUCCESS
+}
+
+/// The battery current, positive when charging.
+fn current_a(ext_power: bool, is_charging: bool) -> f32 {
+    match (ext_power, is_charging) {
+        (true, true) => CHARGE_CURRENT_A,
+        (true, false) => 0.0,
+        (false, _) => -LOAD_CURRENT_A,
+    }
+}
+
+// The charger circuit has no current regulation of its own, this is a ballpark figure.
+const CHARGE_CURRENT_A: f32 = 0.2;
+const LOAD_CURRENT_A: f32 = 0.012;
+
+fn hours(instant: Instant) -> f64 {
+    instant.ticks() as f64 / 3.6e9
+}
+
+fn status(state: &State, battery: &Battery, is_charging: bool) -> String {
+    let rtc = &state.rtc;
+    let (v1, v2) = state.bat_voltage;
+    let [cell1, cell2] = &battery.cells;
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}  ext power: {}  charger: {}  {:?}\n\
+        cells: {:.3} V {:.3} V  charge: {:.0}% {:.0}%  {:.1}°C {:.1}°C  power: {:?}",
+        rtc.year,
+        rtc.month,
+        rtc.day,
+        rtc.hour,
+        rtc.minute,
+        rtc.second,
+        if state.ext_power { "on" } else { "off" },
+        if is_charging { "charge" } else { "hold" },
+        state.bat_level,
+        v1,
+        v2,
+        cell1.soc() * 100.0,
+        cell2.soc() * 100.0,
+        cell1.temperature_c(),
+        cell2.temperature_c(),
+        state.power,
+    )
+}
+
+/// Print the display and the status, in place of the previous ones with `redraw`.
+fn draw(frame: Option<&Frame<DIGIT_COUNT>>, status: &str, redraw: bool) {
+    if redraw {
+        // Clear the screen and move the cursor to the top.
+        print!("\x1b[2J\x1b[H");
+    }
+
+    // The digit 0 is the rightmost one.
+    let mut chars = frame.map_or([Char7DP::space(); DIGIT_COUNT], |frame| *frame.chars());
+    chars.reverse();
+    println!("{}\n{}\n", Char7DP::render(&chars), status);
+}